@@ -2,6 +2,7 @@
 
 use std::fmt;
 
+use crate::span::Span;
 use crate::token::{Token, TokenStream};
 use crate::interpret::{Eval, EvalError, EvalResult, StackFrame, Value};
 
@@ -12,91 +13,188 @@ mod literal;
 mod lvalue;
 mod module;
 mod procedure;
+mod record;
 mod statement;
 mod types;
 
-pub use self::expression::{Expr, BlockExpr, BinOp};
+pub use self::expression::{Expr, ExprKind, BlockExpr, BinOp};
 pub use self::function::Function;
 pub use self::item::Item;
 pub use self::literal::Literal;
 pub use self::lvalue::{Deref, LValue};
 pub use self::module::Module;
-pub use self::procedure::{Param, Procedure, ProcDef};
-pub use self::statement::Stmt;
-pub use self::types::Type;
+pub use self::procedure::{Dir, Param, Procedure, ProcDef};
+pub use self::record::Record;
+pub use self::statement::{Arg, Journal, Steps, Stmt, StmtKind};
+pub use self::types::{IntType, Type};
 
 pub type ParseResult<T> = Result<T, ParseError>;
 
+/// A syntax error, along with what the parser was looking at when it occurred.
 #[derive(Debug, Clone)]
-pub enum ParseError {
-	/// Parser reached an unexpected end-of-file.
-	Eof,
-	Empty,
-	Msg(&'static str),
+pub struct ParseError {
+	pub kind: ParseErrorKind,
+	/// Tokens that would have been accepted at this point, if known.
+	pub expected: Vec<Token>,
+	/// The offending token and its source text, or `None` at end-of-file.
+	pub found: Option<(Token, String)>,
+	/// Location of the offending token.
+	pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseErrorKind {
+	/// Something other than what's described was found.
+	Expected(&'static str),
+	/// A character or string literal has an invalid escape sequence.
 	InvalidChar,
+	/// A parameter name appears twice in the same parameter list.
+	DuplicateParam(String),
+	/// A field appears twice in the same record type or literal.
+	DuplicateField(String),
+}
+
+impl ParseError {
+	/// Whether the error was caused by the input ending too early.
+	pub fn is_eof(&self) -> bool {
+		self.found.is_none()
+	}
 }
 
 impl fmt::Display for ParseError {
 	fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
-		match self {
-			ParseError::Eof => fmt.write_str("not end-of-file"),
-			ParseError::Empty => todo!(),
-			ParseError::Msg(s) => fmt.write_str(s),
-			ParseError::InvalidChar => fmt.write_str("valid character literal"),
+		match &self.kind {
+			ParseErrorKind::Expected(s) => write!(fmt, "expected {}", s)?,
+			ParseErrorKind::InvalidChar =>
+				return fmt.write_str("invalid escape sequence in literal"),
+			ParseErrorKind::DuplicateParam(name) =>
+				return write!(fmt, "parameter `{}` is declared more than once", name),
+			ParseErrorKind::DuplicateField(name) =>
+				return write!(fmt, "field `{}` is given more than once", name),
+		}
+		
+		match &self.found {
+			None => fmt.write_str(", found end of file"),
+			Some((Token::Newline, _)) => fmt.write_str(", found newline"),
+			Some((_, slice)) => write!(fmt, ", found `{}`", slice),
 		}
 	}
 }
 
-impl From<&'static str> for ParseError {
-	fn from(msg: &'static str) -> Self { ParseError::Msg(msg) }
-}
-
 #[derive(Clone)]
 pub struct Parser<'src> {
 	pub tokens: TokenStream<'src>,
-	peek: Option<Token>,
-	line: usize,
+	peek: Option<(Token, Span)>,
+	/// The last token returned by `next`, and its span.
+	last: Option<Token>,
+	span: Span,
+	/// Tokens tried with `expect` since the last token was consumed.
+	expected: Vec<Token>,
+	/// Syntax errors that were recovered from.
+	pub errors: Vec<ParseError>,
 }
 
 impl<'src> Parser<'src> {
 	pub fn new(tokens: TokenStream<'src>) -> Self {
-		Parser { tokens, peek: None, line: 1 }
+		Parser {
+			tokens,
+			peek: None,
+			last: None,
+			span: Span::default(),
+			expected: Vec::new(),
+			errors: Vec::new(),
+		}
 	}
 	
 	pub fn slice(&self) -> &str {
-		self.tokens.slice()
+		&self.tokens.source()[self.span.start..self.span.end]
+	}
+	
+	/// Span of the last token consumed.
+	pub fn span(&self) -> Span {
+		self.span
+	}
+	
+	/// Span of the next token, or an empty span at the end of the source.
+	pub fn peek_span(&mut self) -> Span {
+		self.peek();
+		match self.peek {
+			Some((_, span)) => span,
+			None => {
+				let len = self.tokens.source().len();
+				Span::new(len, 0)
+			}
+		}
+	}
+	
+	/// Span covering everything from `start` up to the last token consumed.
+	pub fn span_from(&self, start: Span) -> Span {
+		start.merge(&self.span)
+	}
+	
+	/// Creates an error pointing at the next token.
+	pub fn error(&mut self, msg: &'static str) -> ParseError {
+		self.error_kind(ParseErrorKind::Expected(msg))
+	}
+	
+	/// Creates an error of the given kind pointing at the next token.
+	pub fn error_kind(&mut self, kind: ParseErrorKind) -> ParseError {
+		let span = self.peek_span();
+		let found = self.peek.map(|(token, span)| {
+			(token, self.tokens.source()[span.start..span.end].to_string())
+		});
+		
+		ParseError { kind, expected: self.expected.clone(), found, span }
+	}
+	
+	/// Creates an error pointing at the token that was just consumed.
+	pub fn error_prev(&self, kind: ParseErrorKind) -> ParseError {
+		ParseError {
+			kind,
+			expected: Vec::new(),
+			found: self.last.map(|token| (token, self.slice().to_string())),
+			span: self.span,
+		}
 	}
 	
 	pub fn peek(&mut self) -> Option<&Token> {
 		if self.peek.is_none() {
-			self.peek = self.tokens.next();
+			self.peek = self.tokens.next()
+				.map(|token| (token, self.tokens.span().into()));
 		}
-		self.peek.as_ref()
+		self.peek.as_ref().map(|(token, _)| token)
 	}
 	
 	pub fn next(&mut self) -> Option<Token> {
-		let token = match self.peek {
-			None => self.tokens.next(),
-			Some(_) => self.peek.take(),
+		let next = match self.peek.take() {
+			None => self.tokens.next()
+				.map(|token| (token, self.tokens.span().into())),
+			peeked => peeked,
 		};
 		
-		if token == Some(Token::Newline) {
-			self.line += 1;
-		}
-		
-		token
+		next.map(|(token, span)| {
+			self.last = Some(token);
+			self.span = span;
+			self.expected.clear();
+			token
+		})
 	}
 	
 	pub fn expect(&mut self, tok: Token) -> Option<Token> {
 		if self.peek() == Some(&tok) {
 			self.next()
 		} else {
+			if !self.expected.contains(&tok) {
+				self.expected.push(tok);
+			}
 			None
 		}
 	}
 	
 	pub fn skip_newlines(&mut self) {
-		while self.expect(Token::Newline).is_some() {}
+		while self.peek() == Some(&Token::Newline) {
+			self.next();
+		}
 	}
 	
 	/// Returns the next identifier if any, and advances the iterator if found.
@@ -105,20 +203,90 @@ impl<'src> Parser<'src> {
 			.map(|_| self.slice().to_string())
 	}
 	
-	pub fn parse_file_module(&mut self) -> ParseResult<Vec<Item>> {
+	/// Parses a whole file, reporting every syntax error found in it.
+	pub fn parse_file_module(&mut self) -> Result<Vec<Item>, Vec<ParseError>> {
 		let mut items = Vec::new();
 		
 		while self.peek().is_some() {
-			match self.peek().unwrap() {
-				Token::Newline => {
-					self.next();
-					continue;
+			if self.peek() == Some(&Token::Newline) {
+				self.next();
+				continue;
+			}
+			match self.parse_item() {
+				Ok(item) => items.push(item),
+				Err(e) => {
+					self.errors.push(e);
+					self.recover_item();
 				}
-				_ => {}
 			}
-			items.push(self.parse_item()?);
 		}
 		
-		Ok(items)
+		if self.errors.is_empty() {
+			Ok(items)
+		} else {
+			Err(std::mem::take(&mut self.errors))
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use logos::Logos;
+	
+	use super::*;
+	
+	fn errors(src: &str) -> Vec<ParseError> {
+		Parser::new(Token::lexer(src)).parse_file_module()
+			.err()
+			.unwrap_or_default()
+	}
+	
+	#[test]
+	fn records_found_token_and_span() {
+		let errors = errors("proc main\n\tx += )\nend\n");
+		assert_eq!(errors.len(), 1);
+		
+		let e = &errors[0];
+		assert_eq!(e.kind, ParseErrorKind::Expected("variable name in left-value expression"));
+		assert_eq!(e.found, Some((Token::RParen, ")".to_string())));
+		assert_eq!((e.span.start, e.span.end), (16, 17));
+		assert!(!e.is_eof());
+		assert_eq!(e.to_string(), "expected variable name in left-value expression, found `)`");
+	}
+	
+	#[test]
+	fn records_expected_tokens() {
+		let errors = errors("proc main\n\tx += )\nend\n");
+		assert!(errors[0].expected.contains(&Token::Ident));
+		assert!(errors[0].expected.contains(&Token::LParen));
+	}
+	
+	#[test]
+	fn end_of_file() {
+		let errors = errors("proc main\n\tskip\n");
+		assert_eq!(errors.len(), 1);
+		assert!(errors[0].is_eof());
+		assert_eq!(errors[0].found, None);
+		assert_eq!(errors[0].to_string(), "expected a statement or `end`, found end of file");
+	}
+	
+	#[test]
+	fn found_newline() {
+		let errors = errors("proc main\n\tx +=\nend\n");
+		assert_eq!(errors[0].found.as_ref().map(|(token, _)| *token), Some(Token::Newline));
+		assert!(errors[0].to_string().ends_with(", found newline"));
+	}
+	
+	#[test]
+	fn malformed_block_header_is_one_error() {
+		let sources = [
+			"proc main\n\tfor i in 0..\n\t\tskip\n\tloop\nend\n",
+			"proc main\n\tvar x := 1 +\n\tskip\n\tdrop x := 1\nend\n",
+			"proc main\n\tif x =\n\t\tskip\n\tfi\nend\n",
+			"proc main\n\tfrom var i := \n\t\tskip\n\tuntil drop i := 3\n\tloop\nend\n",
+		];
+		for src in &sources {
+			assert_eq!(errors(src).len(), 1, "in {:?}", src);
+		}
 	}
 }
@@ -1,49 +1,71 @@
 /*!
-Expressions in Rever have 5 levels of precendence. From strongest to weakest:
-1. Parentheses
-2. Function calls
-3. Unary operators: not - (maybe: ! ~)
-4. Exponential operators: ^ << >> shl shr rol ror
-5. Multiplicative operators: * / mod as and
-6. Additive operators: + - or xor
-7. Relational operators: = != ≠ < > <= ≤ >= ≥ in
+Expressions in Rever have 7 levels of precedence. From strongest to weakest:
+1. Atoms: parentheses, function calls, and casts with `as`
+2. Unary operators: not ! -
+3. Exponentiation: ^
+4. Shifts: << >>
+5. Multiplicative operators: * / mod and &
+6. Additive operators: + - or xor |
+7. Relational operators: = != < > <= >=
+
+`^` is right-associative; all other binary operators are left-associative.
+`not` and `!` are the same operator: logical negation on booleans, and bitwise
+complement on integers.
 
 Ideas:
 + Chained relations, a la Python?
 + In `if` statements, conjunctions is `,` and disjunction is `;` (from Prolog).
   + No short-circuiting; like Pascal.
   + Short-circuiting can be achieved using `and` and `or`.
-
-TODO:
-+ Add precedences 2, 
 */
 
+use std::convert::TryFrom;
+use std::mem;
+
 use super::*;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BinOp {
-	// precedence 4
+	// precedence 3
 	Exp,
+	// precedence 4
+	Shl, Shr,
 	// precedence 5
-	Mul, Div, Mod, And,
+	Mul, Div, Mod, And, BitAnd,
 	// precedence 6
-	Add, Sub, Or, //Xor,
+	Add, Sub, Or, Xor, BitOr,
 	// precedence 7
 	Eq, Ne, Lt, Gt, Le, Ge,
 }
 
 #[derive(Debug, Clone)]
-pub enum Expr {
+pub struct Expr {
+	pub kind: ExprKind,
+	pub span: Span,
+}
+
+/// Expressions are equal if they're written the same, wherever they are.
+impl PartialEq for Expr {
+	fn eq(&self, other: &Self) -> bool {
+		self.kind == other.kind
+	}
+}
+
+impl Eq for Expr {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExprKind {
 	// precedence 1
 	Lit(Literal),
 	LVal(LValue),
 	Cast(Box<Expr>, Type),
+	Call(String, Vec<Expr>),
 	
-	// precedence 3
+	// precedence 2
 	Neg(Box<Expr>),
 	Not(Box<Expr>),
 	
-	// binary op, precendeces 4-7
+	// binary op, precedences 3-7
 	BinOp(Box<Expr>, BinOp, Box<Expr>),
 }
 
@@ -57,12 +79,48 @@ pub enum BlockExpr {
 }
 
 impl Expr {
+	pub fn new(kind: ExprKind, span: Span) -> Self {
+		Expr { kind, span }
+	}
+	
+	fn bin_op(left: Expr, op: BinOp, right: Expr) -> Self {
+		let span = left.span.merge(&right.span);
+		Expr::new(ExprKind::BinOp(Box::new(left), op, Box::new(right)), span)
+	}
+	
 	pub fn get_type(&self) -> Option<Type> {
-		match self {
-			Expr::Cast(_, t) => Some(t.clone()),
+		match &self.kind {
+			ExprKind::Cast(_, t) => Some(t.clone()),
 			_ => None // TODO
 		}
 	}
+	
+	/// Whether the expression refers to the variable `name` anywhere,
+	/// including in indices and in closures nested inside it.
+	pub fn mentions(&self, name: &str) -> bool {
+		match &self.kind {
+			ExprKind::Lit(Literal::Array(elems)) =>
+				elems.iter().any(|elem| elem.mentions(name)),
+			ExprKind::Lit(Literal::Record(_, fields)) =>
+				fields.iter().any(|(_, value)| value.mentions(name)),
+			ExprKind::Lit(Literal::Fn(params, body)) =>
+				!params.iter().any(|param| param == name) && body.mentions(name),
+			ExprKind::Lit(_) => false,
+			ExprKind::LVal(lval) =>
+				lval.id == name || lval.ops.iter().any(|deref| match deref {
+					Deref::Index(index) => index.mentions(name),
+					_ => false,
+				}),
+			ExprKind::Call(callee, args) =>
+				callee == name || args.iter().any(|arg| arg.mentions(name)),
+			ExprKind::Cast(expr, _)
+			| ExprKind::Neg(expr)
+			| ExprKind::Not(expr) =>
+				expr.mentions(name),
+			ExprKind::BinOp(left, _, right) =>
+				left.mentions(name) || right.mentions(name),
+		}
+	}
 }
 
 impl Parser<'_> {
@@ -74,24 +132,24 @@ impl Parser<'_> {
 				let test = self.parse_expr()?;
 				
 				self.expect(Token::Newline)
-					.ok_or("newline after `if` predicate")?;
+					.ok_or_else(|| self.error("newline after `if` predicate"))?;
 				
 				// parse main block
 				let main_expr = Box::new(self.parse_block_expr()?);
 				
 				self.expect(Token::Else)
-					.ok_or("`else` in `if` expression")?;
+					.ok_or_else(|| self.error("`else` in `if` expression"))?;
 				
 				match self.peek() {
 					Some(Token::If) => {}
 					Some(Token::Newline) => { self.next(); }
-					_ => Err("`if` or newline after `else`")?,
+					_ => Err(self.error("`if` or newline after `else`"))?,
 				}
 				
 				let else_block = Box::new(self.parse_block_expr()?);
 				
 				self.expect(Token::Fi)
-					.ok_or("`fi` in `if` expression")?;
+					.ok_or_else(|| self.error("`fi` in `if` expression"))?;
 				
 				BlockExpr::If(test, main_expr, else_block)
 			}
@@ -99,7 +157,7 @@ impl Parser<'_> {
 				self.next();
 				
 				let name = self.expect_ident()
-					.ok_or("variable name for let binding")?;
+					.ok_or_else(|| self.error("variable name for let binding"))?;
 				
 				// get optional `: <type>`
 				let typ = match self.expect(Token::Colon) {
@@ -109,38 +167,32 @@ impl Parser<'_> {
 				
 				// expect '='
 				self.expect(Token::Eq)
-					.ok_or("`=` at let binding")?;
+					.ok_or_else(|| self.error("`=` at let binding"))?;
 				
 				let val = self.parse_expr()?;
 				
 				self.expect(Token::Newline)
-					.ok_or("newline at let binding")?;
+					.ok_or_else(|| self.error("newline at let binding"))?;
 				
+				// the scope already ends with a newline
 				let scope = Box::new(self.parse_block_expr()?);
 				
-				BlockExpr::Let(name, typ, val, scope)
+				return Ok(BlockExpr::Let(name, typ, val, scope));
 			}
 			Some(_) =>
 				BlockExpr::Expr(self.parse_expr()?),
 			None =>
-				todo!()
+				Err(self.error("an expression"))?
 		};
 		
 		self.expect(Token::Newline)
-			.ok_or("newline after expression block")?;
+			.ok_or_else(|| self.error("newline after expression block"))?;
 		
 		self.skip_newlines();
 		
 		Ok(block_expr)
 	}
 	
-	// rel  -> expr {(=|≠|<|>|≤|≥|in) expr}
-	// expr -> term {(+|-|or) term}
-	// term -> exp {(*|/|mod|and) exp}
-	// exp  -> atom {^ atom}
-	// atom -> ( expr )
-	//      -> expr 'as' type
-	//      -> factor
 	pub fn parse_expr(&mut self) -> ParseResult<Expr> {
 		// <term>
 		let first = self.parse_expr_add()?;
@@ -169,7 +221,7 @@ impl Parser<'_> {
 		
 		let expr = exprs.into_iter()
 			.fold(first, |acc, (op, base)| {
-				Expr::BinOp(Box::new(acc), op, Box::new(base))
+				Expr::bin_op(acc, op, base)
 			});
 		
 		Ok(expr)
@@ -180,12 +232,14 @@ impl Parser<'_> {
 		let first = self.parse_expr_mul()?;
 		let mut terms: Vec<(BinOp, Expr)> = Vec::new();
 		
-		// { ('+' | '-' | 'or' | ':') <term> }
+		// { ('+' | '-' | 'or' | 'xor' | '|') <term> }
 		loop {
 			let op = match self.peek() {
 				Some(Token::Plus)  => BinOp::Add,
 				Some(Token::Minus) => BinOp::Sub,
 				Some(Token::Or)    => BinOp::Or,
+				Some(Token::Xor)   => BinOp::Xor,
+				Some(Token::Pipe)  => BinOp::BitOr,
 			    _ => break
 			};
 			self.next();
@@ -200,7 +254,7 @@ impl Parser<'_> {
 		
 		let expr = terms.into_iter()
 			.fold(first, |acc, (op, base)| {
-				Expr::BinOp(Box::new(acc), op, Box::new(base))
+				Expr::bin_op(acc, op, base)
 			});
 		
 		Ok(expr)
@@ -208,21 +262,22 @@ impl Parser<'_> {
 	
 	pub fn parse_expr_mul(&mut self) -> ParseResult<Expr> {
 		// <fact>
-		let first = self.parse_expr_exp()?;
+		let first = self.parse_expr_shift()?;
 		let mut facts: Vec<(BinOp, Expr)> = Vec::new();
 		
-		// { ('*' | '/' | 'mod' | 'and') <fact> }
+		// { ('*' | '/' | 'mod' | 'and' | '&') <fact> }
 		loop {
 			let op = match self.peek() {
 				Some(Token::Star)   => BinOp::Mul,
 				Some(Token::FSlash) => BinOp::Div,
-				Some(Token::Mod)    => BinOp::Mod,
+				Some(Token::Modulo) => BinOp::Mod,
 				Some(Token::And)    => BinOp::And,
+				Some(Token::Amp)    => BinOp::BitAnd,
 				_ => break
 			};
 			self.next();
 			
-			let fact = self.parse_expr_exp()?;
+			let fact = self.parse_expr_shift()?;
 			facts.push((op, fact));
 		}
 		
@@ -232,26 +287,40 @@ impl Parser<'_> {
 		
 		let expr = facts.into_iter()
 			.fold(first, |acc, (op, base)| {
-				Expr::BinOp(Box::new(acc), op, Box::new(base))
+				Expr::bin_op(acc, op, base)
 			});
 		
 		Ok(expr)
 	}
 	
-	pub fn parse_expr_exp(&mut self) -> ParseResult<Expr> {
+	pub fn parse_expr_shift(&mut self) -> ParseResult<Expr> {
 		// <exp>
-		let first = self.parse_expr_atom()?;
-		let mut exps = Vec::new();
+		let mut expr = self.parse_expr_exp()?;
 		
-		// { ('^') <exp> }
+		// { ('<<' | '>>') <exp> }
 		loop {
 			let op = match self.peek() {
-				Some(Token::Caret) => {}
+				Some(Token::Shl) => BinOp::Shl,
+				Some(Token::Shr) => BinOp::Shr,
 				_ => break
 			};
 			self.next();
 			
-			let exp = self.parse_expr_atom()?;
+			let exp = self.parse_expr_exp()?;
+			expr = Expr::bin_op(expr, op, exp);
+		}
+		
+		Ok(expr)
+	}
+	
+	pub fn parse_expr_exp(&mut self) -> ParseResult<Expr> {
+		// <unary>
+		let first = self.parse_expr_unary()?;
+		let mut exps = Vec::new();
+		
+		// { ('^') <unary> }
+		while self.expect(Token::Caret).is_some() {
+			let exp = self.parse_expr_unary()?;
 			exps.push(exp);
 		}
 		
@@ -261,13 +330,41 @@ impl Parser<'_> {
 		
 		let last = exps.pop().unwrap();
 		let res = exps.into_iter().rfold(last, |acc, base| {
-			Expr::BinOp(Box::new(base), BinOp::Exp, Box::new(acc))
+			Expr::bin_op(base, BinOp::Exp, acc)
 		});
 		
-		Ok(Expr::BinOp(Box::new(first), BinOp::Exp, Box::new(res)))
+		Ok(Expr::bin_op(first, BinOp::Exp, res))
+	}
+	
+	pub fn parse_expr_unary(&mut self) -> ParseResult<Expr> {
+		let start = self.peek_span();
+		
+		let neg = match self.peek() {
+			Some(Token::Minus) => true,
+			Some(Token::Not) | Some(Token::Bang) => false,
+			_ => return self.parse_expr_atom(),
+		};
+		self.next();
+		
+		let expr = self.parse_expr_unary()?;
+		let kind = match expr.kind {
+			// negative number literals, so that e.g. `-9223372036854775808`
+			// is a valid `int`
+			ExprKind::Lit(lit) if neg => match lit {
+				Literal::Int(n) if n != i64::MIN => ExprKind::Lit(Literal::Int(-n)),
+				Literal::UInt(n) if n == 1 << 63 => ExprKind::Lit(Literal::Int(i64::MIN)),
+				Literal::BigInt(n) => ExprKind::Lit(Literal::BigInt(-n)),
+				lit => ExprKind::Neg(Box::new(Expr::new(ExprKind::Lit(lit), expr.span))),
+			}
+			kind if neg => ExprKind::Neg(Box::new(Expr::new(kind, expr.span))),
+			kind => ExprKind::Not(Box::new(Expr::new(kind, expr.span))),
+		};
+		Ok(Expr::new(kind, self.span_from(start)))
 	}
 	
 	pub fn parse_expr_atom(&mut self) -> ParseResult<Expr> {
+		let start = self.peek_span();
+		
 		// check if there's an open parenthesis
 		let mut expr =
 			if self.expect(Token::LParen).is_some() {
@@ -275,58 +372,163 @@ impl Parser<'_> {
 				
 				// make sure there's a closing parenthesis
 				self.expect(Token::RParen)
-					.ok_or("`)` after subexpression")?;
+					.ok_or_else(|| self.error("`)` after subexpression"))?;
 				
-				expr
+				Expr::new(expr.kind, self.span_from(start))
 			} else {
 				// otherwise, treat it as a Term.
 				let mut clone = self.clone();
+				let after_ident = clone.expect_ident()
+					.and_then(|_| clone.peek().copied());
+				let is_call = after_ident == Some(Token::LParen);
+				let is_record = after_ident == Some(Token::LBrace);
 				
-				if clone.parse_lit().is_ok() {
-					Expr::Lit(self.parse_lit()?)
+				let mut clone = self.clone();
+				
+				let kind = if is_call {
+					let name = self.expect_ident().unwrap();
+					ExprKind::Call(name, self.parse_call_args()?)
+				} else if is_record || clone.parse_lit().is_ok() {
+					ExprKind::Lit(self.parse_lit()?)
 				} else {
-					Expr::LVal(self.parse_lval()?)
-				}
+					ExprKind::LVal(self.parse_lval()?)
+				};
+				Expr::new(kind, self.span_from(start))
 			};
 		
 		// check for `as` casting
-		Ok(loop {
-			if self.expect(Token::As).is_some() {
-				expr = Expr::Cast(Box::new(expr), self.parse_type()?)
-			} else {
-				break expr
+		while self.expect(Token::As).is_some() {
+			let typ = self.parse_type()?;
+			expr = Expr::new(ExprKind::Cast(Box::new(expr), typ), self.span_from(start));
+		}
+		
+		Ok(expr)
+	}
+	
+	/// Parses the parenthesized argument list of a function call.
+	fn parse_call_args(&mut self) -> ParseResult<Vec<Expr>> {
+		self.expect(Token::LParen)
+			.ok_or_else(|| self.error("`(` before function arguments"))?;
+		
+		let mut args = Vec::new();
+		loop {
+			match self.peek() {
+				Some(Token::RParen) => break,
+				Some(_) => {
+					args.push(self.parse_expr()?);
+					
+					match self.peek() {
+						Some(Token::Comma) => { self.next(); }
+						Some(Token::RParen) => {}
+						_ => Err(self.error("`,` or `)` after function argument"))?,
+					}
+				}
+				None => Err(self.error("`,` or `)` after function argument"))?,
 			}
-		})
+		}
+		self.next();
+		
+		Ok(args)
 	}
 }
 
 
-// rel  -> expr {(=|≠|<|>|≤|≥|in) expr}
-// expr -> term {(+|-|or) term}
-// term -> exp {(*|/|mod|and) exp}
-// exp  -> atom {^ atom}
-// atom -> ( expr )
-//      -> expr 'as' type
-//      -> factor
+// rel   -> expr {(=|!=|<|>|<=|>=) expr}
+// expr  -> term {(+|-|or|xor|'|') term}
+// term  -> shift {(*|/|mod|and|&) shift}
+// shift -> exp {(<<|>>) exp}
+// exp   -> unary [^ exp]
+// unary -> (-|not|!) unary
+//       -> atom
+// atom  -> ( rel )
+//       -> ident ( [rel {, rel}] )
+//       -> ident { [ident : rel {, ident : rel}] }
+//       -> atom 'as' type
+//       -> literal
+//       -> lvalue
 impl Eval for Expr {
-	fn eval(&self, t: &StackFrame) -> EvalResult<Value> {
-		match self {
-			Expr::Lit(lit) => lit.eval(t),
-			Expr::LVal(lval) => lval.eval(t),
+	fn eval(&self, t: &StackFrame, m: &Module) -> EvalResult<Value> {
+		match &self.kind {
+			ExprKind::Lit(lit) => lit.eval(t, m),
+			ExprKind::LVal(lval) => lval.eval(t, m),
 			
-			Expr::Cast(e, typ) => match (typ, e.eval(t)?) {
+			ExprKind::Cast(e, typ) => match (typ, e.eval(t, m)?) {
 				(Type::Unit, _) => Ok(Value::Nil),
+				// a value already of the type is left alone
+				(typ, value) if value.get_type() == *typ => Ok(value),
+				// conversions between integers wrap around, like in Rust
+				(Type::Fixed(to), Value::Fixed(from, bits)) =>
+					Ok(Value::Fixed(*to, to.wrap(from.to_i128(bits) as u64))),
+				(Type::Fixed(to), Value::Int(i)) => Ok(Value::Fixed(*to, to.wrap(i as u64))),
+				(Type::Fixed(to), Value::Uint(u)) => Ok(Value::Fixed(*to, to.wrap(u))),
+				(Type::Fixed(to), Value::Bool(b)) => Ok(Value::Fixed(*to, b as u64)),
+				(Type::Fixed(to), Value::Char(c)) => Ok(Value::Fixed(*to, to.wrap(c as u64))),
+				(Type::Int, Value::Fixed(from, bits)) => Ok(Value::Int(from.to_i128(bits) as i64)),
+				(Type::UInt, Value::Fixed(from, bits)) => Ok(Value::Uint(from.to_i128(bits) as u64)),
+				(Type::BigInt, Value::Int(i)) => Ok(Value::BigInt(i.into())),
+				(Type::BigInt, Value::Uint(u)) => Ok(Value::BigInt(u.into())),
+				(Type::BigInt, Value::Fixed(from, bits)) => Ok(Value::BigInt(from.to_i128(bits).into())),
+				// a `bigint` only converts to a smaller type if it fits
+				(Type::Int, Value::BigInt(n)) => match i64::try_from(&n) {
+					Ok(i) => Ok(Value::Int(i)),
+					Err(_) => Err(EvalError::IntOutOfRange {
+						value: Value::BigInt(n),
+						typ: Type::Int,
+					}),
+				}
+				(Type::UInt, Value::BigInt(n)) => match u64::try_from(&n) {
+					Ok(u) => Ok(Value::Uint(u)),
+					Err(_) => Err(EvalError::IntOutOfRange {
+						value: Value::BigInt(n),
+						typ: Type::UInt,
+					}),
+				}
+				(Type::Fixed(to), Value::BigInt(n)) => match i128::try_from(&n).ok()
+					.and_then(|i| to.checked_bits(i))
+				{
+					Some(bits) => Ok(Value::Fixed(*to, bits)),
+					None => Err(EvalError::IntOutOfRange {
+						value: Value::BigInt(n),
+						typ: Type::Fixed(*to),
+					}),
+				}
 				(Type::Int, Value::Uint(u))  => Ok(Value::Int(u as i64)),
 				(Type::UInt, Value::Bool(b)) => Ok(Value::Uint(b as u64)),
 				(Type::UInt, Value::Int(i))  => Ok(Value::Uint(i as u64)),
 				(Type::Char, Value::Int(i)) => Ok(Value::Char(i as u8 as char)),
 				(Type::Char, Value::Uint(i)) => Ok(Value::Char(i as u8 as char)),
 				(Type::String, Value::Char(c)) => Ok(Value::String(c.to_string())),
-				(typ, value) => panic!("tried casting {} to {:?}", value, typ),
+				// `nil as ref T` is a null reference
+				(Type::Ref(t), Value::Nil) => Ok(Value::Ref((**t).clone(), None)),
+				(typ, value) => Err(EvalError::InvalidCast {
+					from: value.get_type(),
+					to: typ.clone(),
+				}),
 			}
 			
-			Expr::Not(e) => match e.eval(t)? {
+			ExprKind::Call(name, args) => {
+				let mut vals = Vec::with_capacity(args.len());
+				for arg in args {
+					vals.push(arg.eval(t, m)?);
+				}
+				
+				// variables holding closures shadow functions
+				match t.lookup(name) {
+					Some(Value::Fn(closure)) => closure.call(name, vals, m),
+					Some(val) => Err(EvalError::TypeMismatch {
+						expected: Type::Fn(vec![Type::Infer; args.len()], Box::new(Type::Infer)),
+						got: val.get_type(),
+					}),
+					None => m.get_fn(name)
+						.ok_or_else(|| EvalError::UnknownFn(name.clone()))?
+						.call(vals, m),
+				}
+			}
+			
+			ExprKind::Not(e) => match e.eval(t, m)? {
 				Value::Bool(b) => Ok(Value::Bool(!b)),
+				Value::Fixed(int_type, bits) => Ok(Value::Fixed(int_type, int_type.wrap(!bits))),
+				Value::BigInt(n) => Ok(Value::BigInt(!n)),
 				Value::Uint(n) => Ok(Value::Uint(!n)),
 				Value::Int(n) => Ok(Value::Int(!n)),
 				val => Err(EvalError::TypeMismatch {
@@ -335,68 +537,60 @@ impl Eval for Expr {
 				})
 			}
 			
-			Expr::Neg(e) => match e.eval(t)? {
-				Value::Int(n) => Ok(Value::Int(n.wrapping_neg())),
+			ExprKind::Neg(e) => match e.eval(t, m)? {
+				Value::Int(n) => Value::int_neg(n, m.checked),
+				Value::Fixed(int_type, bits) => Ok(Value::Fixed(int_type, int_type.wrap(bits.wrapping_neg()))),
+				Value::BigInt(n) => Ok(Value::BigInt(-n)),
 				val => Err(EvalError::TypeMismatch {
 					expected: Type::Int,
 					got: val.get_type(),
 				})
 			}
 			
-			Expr::BinOp(left, op, right) => {
-				let left = left.eval(t)?;
-				let right = right.eval(t)?;
+			ExprKind::BinOp(left, op, right) => {
+				let left = left.eval(t, m)?;
+				let right = right.eval(t, m)?;
+				
+				match (&left, &right) {
+					(Value::Fixed(int_type, _), _) | (_, Value::Fixed(int_type, _)) =>
+						return Value::fixed_bin_op(*op, *int_type, &left, &right),
+					(Value::BigInt(_), _) | (_, Value::BigInt(_)) =>
+						return Value::big_bin_op(*op, &left, &right),
+					(Value::Uint(_), Value::Int(_)) | (Value::Int(_), Value::Uint(_)) =>
+						return Value::mixed_bin_op(*op, &left, &right, m.checked),
+					_ => {}
+				}
 				
 				match (op, left, right) {
-					// 4
-					(BinOp::Exp, Value::Int(l), Value::Int(r)) =>
-						Ok(Value::from(l.pow(r as u32))),
+					(op, Value::Int(l), Value::Int(r)) =>
+						Value::int_bin_op(*op, l, r, m.checked),
+					(op, Value::Uint(l), Value::Uint(r)) =>
+						Value::uint_bin_op(*op, l, r, m.checked),
 					
-					// 5
-					(BinOp::Mul, Value::Int(l), Value::Int(r)) =>
-						Ok(Value::from(l * r)),
-					(BinOp::Div, Value::Int(l), Value::Int(r)) =>
-						Ok(Value::from(l / r)),
-					(BinOp::Mod, Value::Int(l), Value::Int(r)) =>
-						Ok(Value::from((l % r + r) % r)),
 					(BinOp::And, Value::Bool(l), Value::Bool(r)) =>
 						Ok(Value::from(l && r)),
-					
-					// 6
-					(BinOp::Add, Value::Int(l), Value::Int(r)) =>
-						Ok(Value::from(l + r)),
-					(BinOp::Sub, Value::Int(l), Value::Int(r)) =>
-						Ok(Value::from(l - r)),
 					(BinOp::Or, Value::Bool(l), Value::Bool(r)) =>
 						Ok(Value::from(l || r)),
-					/*
 					(BinOp::Xor, Value::Bool(l), Value::Bool(r)) =>
 						Ok(Value::from(l ^ r)),
-					(BinOp::Xor, Value::Int(l), Value::Int(r)) =>
-						Ok(Value::from(l ^ r)),
-					*/
+					(BinOp::BitAnd, Value::Bool(l), Value::Bool(r)) =>
+						Ok(Value::from(l & r)),
+					(BinOp::BitOr, Value::Bool(l), Value::Bool(r)) =>
+						Ok(Value::from(l | r)),
 					
-					// 7
-					(BinOp::Eq, l, r) =>
+					// values of different kinds are never equal, which is
+					// more likely a mistake than intended
+					(BinOp::Eq, l, r) if mem::discriminant(&l) == mem::discriminant(&r) =>
 						Ok(Value::from(l == r)),
-					(BinOp::Ne, l, r) =>
+					(BinOp::Ne, l, r) if mem::discriminant(&l) == mem::discriminant(&r) =>
 						Ok(Value::from(l != r)),
-					(BinOp::Lt, Value::Int(l), Value::Int(r)) =>
-						Ok(Value::from(l < r)),
-					(BinOp::Gt, Value::Int(l), Value::Int(r)) =>
-						Ok(Value::from(l > r)),
-					(BinOp::Le, Value::Int(l), Value::Int(r)) =>
-						Ok(Value::from(l <= r)),
-					(BinOp::Ge, Value::Int(l), Value::Int(r)) =>
-						Ok(Value::from(l >= r)),
 					
 					(op, left, right) =>
-						panic!(
-							"tried to do a {:?} operation with types {:?} and {:?}",
-							op,
-							left.get_type(),
-							right.get_type(),
-						),
+						Err(EvalError::InvalidOperands {
+							op: *op,
+							left: left.get_type(),
+							right: right.get_type(),
+						}),
 				}
 			}
 		}
@@ -404,23 +598,23 @@ impl Eval for Expr {
 }
 
 impl Eval for BlockExpr {
-	fn eval(&self, t: &StackFrame) -> EvalResult<Value> {
+	fn eval(&self, t: &StackFrame, m: &Module) -> EvalResult<Value> {
 		match self {
-			BlockExpr::Expr(expr) => expr.eval(t),
+			BlockExpr::Expr(expr) => expr.eval(t, m),
 			
 			BlockExpr::If(test, expr, else_expr) => {
-				if test.eval(t)? == Value::Bool(true) {
-					expr.eval(t)
+				if test.eval(t, m)? == Value::Bool(true) {
+					expr.eval(t, m)
 				} else {
-					else_expr.eval(t)
+					else_expr.eval(t, m)
 				}
 			}
 			
 			BlockExpr::Let(name, _, val, scope) => {
-				let val = val.eval(t)?;
+				let val = val.eval(t, m)?;
 				let mut t_copy = t.clone();
 				t_copy.push(name.clone(), val);
-				scope.eval(&t_copy)
+				scope.eval(&t_copy, m)
 			}
 		}
 	}
@@ -6,6 +6,7 @@ pub struct Function {
     pub params: Vec<(String, Type)>,
     pub ret: Type,
     pub body: BlockExpr,
+    pub span: Span,
 }
 
 // param ::= ident [":" type]
@@ -16,19 +17,21 @@ pub struct Function {
 //    ::= "fn" ident "(" params ")" ":" type "=" line-expr
 impl Parser<'_> {
 	pub fn parse_fn(&mut self) -> ParseResult<Function> {
+		let start = self.peek_span();
+		
 		// keyword `fn`
-		self.expect(Token::Fn).ok_or("`fn`")?;
+		self.expect(Token::Fn).ok_or_else(|| self.error("`fn`"))?;
 		
 		// function name
 		let fn_name = self.expect_ident()
-			.ok_or("function name")?;
+			.ok_or_else(|| self.error("function name"))?;
 		
 		// parse parameter list
 		let mut params = Vec::new();
 		
 		// starting '('
 		self.expect(Token::LParen)
-			.ok_or("`(` before parameter list")?;
+			.ok_or_else(|| self.error("`(` before parameter list"))?;
 		
 		loop {
 			// TODO add case for newline for multiline param declaration?
@@ -40,7 +43,15 @@ impl Parser<'_> {
 				Some(_) => {
 					// get parameter name
 					let param_name = self.expect_ident()
-						.ok_or("a parameter name")?;
+						.ok_or_else(|| self.error("a parameter name"))?;
+					
+					// ensure param name is unique
+					// TODO leave until hir translation?
+					if params.iter().any(|(name, _)| *name == param_name) {
+						return Err(self.error_prev(
+							ParseErrorKind::DuplicateParam(param_name)
+						));
+					}
 					
 					// get optional type
 					let typ = match self.expect(Token::Colon) {
@@ -48,36 +59,24 @@ impl Parser<'_> {
 						None => Type::Infer,
 					};
 					
-					// ensure param name is unique
-					// TODO leave until hir translation?
-					for (name, _) in &params {
-						if *name == param_name {
-							eprintln!(
-								"A parameter name in `fn {}` was repeated: {:?}",
-								fn_name, param_name
-							);
-							Err("parameter names to be unique")?;
-						}
-					}
-					
 					// push to list of parameters
 					params.push((param_name, typ));
 					
 					match self.peek() {
 						Some(Token::Comma) => { self.next(); }
 						Some(Token::RParen) => {}
-						_ => Err("`,` or `)`")?,
+						_ => Err(self.error("`,` or `)`"))?,
 					}
 				}
 				
-				None => Err("`,` or `)`")?,
+				None => Err(self.error("`,` or `)`"))?,
 			}
 		}
 		self.next();
 		
 		// get return type
 		self.expect(Token::Colon)
-			.ok_or("`:` after function parameters")?;
+			.ok_or_else(|| self.error("`:` after function parameters"))?;
 		
 		let ret = self.parse_type()?;
 		
@@ -89,14 +88,12 @@ impl Parser<'_> {
 			Some(Token::Newline) => {
 				self.next();
 				
+				// the body includes the newline before `end`
 				let body = self.parse_block_expr()?;
-		
-				self.expect(Token::Newline)
-					.ok_or("newline after function body")?;
 				
 				// reached `end`
 				self.expect(Token::End)
-					.ok_or("`end` after function body")?;
+					.ok_or_else(|| self.error("`end` after function body"))?;
 				
 				body
 			}
@@ -107,15 +104,35 @@ impl Parser<'_> {
 				
 				let body = self.parse_expr()?;
 				
-				self.expect(Token::Newline)
-					.ok_or("newline after function body")?;
-				
 				BlockExpr::Expr(body)
 			}
 			
-			_ => Err("`=` or newline after function declaration")?,
+			_ => Err(self.error("`=` or newline after function declaration"))?,
 		};
 		
-		Ok(Function { name: fn_name, params, body, ret })
+		Ok(Function { name: fn_name, params, body, ret, span: self.span_from(start) })
 	}
 }
+
+impl Function {
+	/// Evaluates the body of the function with its parameters bound to
+	/// `args`. The body can't see any variables of the caller.
+	pub fn call(&self, args: Vec<Value>, m: &Module) -> EvalResult<Value> {
+		if args.len() != self.params.len() {
+			return Err(EvalError::ArityMismatch {
+				name: self.name.clone(),
+				expected: self.params.len(),
+				got: args.len(),
+			});
+		}
+		
+		let mut frame = Vec::with_capacity(args.len());
+		for ((name, typ), arg) in self.params.iter().zip(args) {
+			frame.push((name.clone(), arg.conform(typ)?));
+		}
+		let frame = StackFrame::new(frame);
+		
+		self.body.eval(&frame, m)?.conform(&self.ret)
+	}
+}
+
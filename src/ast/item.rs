@@ -7,6 +7,7 @@ pub enum Item {
 	Mod(Module),
 	Proc(Procedure),
 	Fn(Function),
+	Record(Record),
 	//Type(Type),
 }
 
@@ -16,6 +17,16 @@ impl Item {
 			Item::Mod(m)  => &m.name,
 			Item::Proc(p) => &p.name,
 			Item::Fn(f)   => &f.name,
+			Item::Record(r) => &r.name,
+		}
+	}
+	
+	pub fn span(&self) -> Span {
+		match self {
+			Item::Mod(m)  => m.span,
+			Item::Proc(p) => p.span,
+			Item::Fn(f)   => f.span,
+			Item::Record(r) => r.span,
 		}
 	}
 }
@@ -26,14 +37,15 @@ impl Parser<'_> {
 			Some(Token::Proc) => Item::Proc(self.parse_proc()?),
 			Some(Token::Mod)  => Item::Mod(self.parse_mod()?),
 			Some(Token::Fn)   => Item::Fn(self.parse_fn()?),
+			Some(Token::Struct) => Item::Record(self.parse_record()?),
 			
-			_ => Err("a module, function, or procedure")?,
+			_ => Err(self.error("a module, function, procedure, or record"))?,
 		};
 		
 		// mandatory newline (or EOF) after item
 		match self.peek() {
 			Some(Token::Newline) | None => {}
-			Some(_) => Err("newline after item")?,
+			Some(_) => Err(self.error("newline after item"))?,
 		}
 		
 		// eat all extra newlines
@@ -41,6 +53,22 @@ impl Parser<'_> {
 		
 		Ok(item)
 	}
+	
+	/// Skips ahead to the next line that starts an item after a syntax error.
+	pub fn recover_item(&mut self) {
+		while let Some(token) = self.next() {
+			if token == Token::Newline {
+				match self.peek() {
+					Some(Token::Proc)
+					| Some(Token::Fn)
+					| Some(Token::Mod)
+					| Some(Token::Struct)
+					| None => break,
+					_ => {}
+				}
+			}
+		}
+	}
 }
 
 use std::fmt;
@@ -50,6 +78,7 @@ impl fmt::Debug for Item {
 			Item::Fn(f)   => f.fmt(fmt),
 			Item::Proc(p) => p.fmt(fmt),
 			Item::Mod(m)  => m.fmt(fmt),
+			Item::Record(r) => r.fmt(fmt),
 		}
 	}
 }
@@ -1,15 +1,26 @@
 use super::*;
 
-#[derive(Debug, Clone)]
+use std::convert::TryFrom;
+use std::rc::Rc;
+
+use num_bigint::BigInt;
+
+use crate::interpret::Closure;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Literal {
 	Nil,
 	Bool(bool),
 	Int(i64),
 	UInt(u64),
+	/// A number too big even for `u64`.
+	BigInt(BigInt),
 	Char(char),
 	String(String),
 	Array(Vec<Expr>),
 	Fn(Vec<String>, Box<Expr>),
+	/// `Name { field: value, ... }`, with fields in any order.
+	Record(String, Vec<(String, Expr)>),
 }
 
 impl Parser<'_> {
@@ -17,19 +28,36 @@ impl Parser<'_> {
 		Ok(match self.peek() {
 			Some(Token::Ident) => {
 				self.next();
+				let is_record = self.peek() == Some(&Token::LBrace);
 				match self.slice() {
 					"nil" => Literal::Nil,
 					"true" => Literal::Bool(true),
 					"false" => Literal::Bool(false),
-					_ => Err("`nil`, `true`, or `false`")?
+					name if is_record => {
+						let name = name.to_string();
+						Literal::Record(name, self.parse_record_fields()?)
+					}
+					_ => return Err(self.error_prev(
+						ParseErrorKind::Expected("`nil`, `true`, `false`, or a record")
+					)),
 				}
 			}
 			
 			Some(Token::Number) => {
 				self.next();
-				match i64::from_str_radix(self.slice(), 10) {
-					Ok(n) => Literal::Int(n),
-					Err(_) => Err("a smaller number")?,
+				let n = match self.slice().strip_prefix("0x") {
+					Some(hex) => BigInt::parse_bytes(hex.as_bytes(), 16),
+					None => BigInt::parse_bytes(self.slice().as_bytes(), 10),
+				};
+				let n = n.ok_or_else(|| self.error_prev(
+					ParseErrorKind::Expected("a number")
+				))?;
+				
+				// use the smallest type the number fits in
+				match (i64::try_from(&n), u64::try_from(&n)) {
+					(Ok(n), _) => Literal::Int(n),
+					(_, Ok(n)) => Literal::UInt(n),
+					_ => Literal::BigInt(n),
 				}
 			}
 			
@@ -47,16 +75,16 @@ impl Parser<'_> {
 						Some('t') => '\t',
 						Some('r') => '\r',
 						Some('0') => '\0',
-						_ => return Err(ParseError::InvalidChar),
+						_ => return Err(self.invalid_char()),
 					}
 					Some(c) if ! "\\\'\n\t\r\0".contains(c) => c,
-					_ => return Err(ParseError::InvalidChar),
+					_ => return Err(self.invalid_char()),
 				};
 				
 				match chars.next() {
 					Some('\'') => {}
-					Some(c) => return Err(ParseError::InvalidChar),
-					None => return Err(ParseError::Eof),
+					Some(c) => return Err(self.invalid_char()),
+					None => return Err(self.invalid_char()),
 				}
 				
 				Literal::Char(c)
@@ -92,12 +120,12 @@ impl Parser<'_> {
 							Some('0')  => '\0',
 							
 							Some(c) =>
-								return Err(ParseError::InvalidChar),
+								return Err(self.invalid_char()),
 							None =>
-								return Err(ParseError::Eof),
+								return Err(self.invalid_char()),
 						}),
 						Some(c) => string.push(c),
-						None => return Err(ParseError::Eof),
+						None => return Err(self.invalid_char()),
 					}
 				}
 				
@@ -121,10 +149,10 @@ impl Parser<'_> {
 							match self.peek() {
 								Some(Token::Comma) => { self.next(); }
 								Some(Token::RBracket) => {}
-								_ => Err("`,` or `]` after element in array")?,
+								_ => Err(self.error("`,` or `]` after element in array"))?,
 							}
 						}
-						None => Err("`,` or `]` after element in array")?,
+						None => Err(self.error("`,` or `]` after element in array"))?,
 					}
 				}
 				self.next();
@@ -137,7 +165,7 @@ impl Parser<'_> {
 				self.next();
 				
 				self.expect(Token::LParen)
-					.ok_or("`(` at start of closure")?;
+					.ok_or_else(|| self.error("`(` at start of closure"))?;
 				
 				let mut args = Vec::new();
 				loop {
@@ -150,45 +178,120 @@ impl Parser<'_> {
 							match self.peek() {
 								Some(Token::Comma) => { self.next(); }
 								Some(Token::RParen) => {}
-								_ => Err("`,` or `)` after argument name in closure")?,
+								_ => Err(self.error("`,` or `)` after argument name in closure"))?,
 							}
 						}
-						_ => Err("`,` or `)` after argument name in closure")?,
+						_ => Err(self.error("`,` or `)` after argument name in closure"))?,
 					}
 				}
 				self.next();
 				
 				self.expect(Token::Colon)
-					.ok_or("`:` after arguments in closure")?;
+					.ok_or_else(|| self.error("`:` after arguments in closure"))?;
 				
 				let expr = self.parse_expr()?;
 				
 				Literal::Fn(args, Box::new(expr))
 			}
 			
-			_ => Err("valid literal value")?
+			_ => Err(self.error("valid literal value"))?
 		})
 	}
 }
 
+impl Parser<'_> {
+	/// Parses the `{ field: value, ... }` part of a record literal. Newlines
+	/// may appear between fields, and a trailing comma is allowed.
+	fn parse_record_fields(&mut self) -> ParseResult<Vec<(String, Expr)>> {
+		self.expect(Token::LBrace)
+			.ok_or_else(|| self.error("`{` before record fields"))?;
+		self.skip_newlines();
+		
+		let mut fields: Vec<(String, Expr)> = Vec::new();
+		loop {
+			match self.peek() {
+				Some(Token::RBrace) => break,
+				Some(Token::Ident) => {
+					let field = self.expect_ident().unwrap();
+					
+					if fields.iter().any(|(name, _)| *name == field) {
+						return Err(self.error_prev(ParseErrorKind::DuplicateField(field)));
+					}
+					
+					self.expect(Token::Colon)
+						.ok_or_else(|| self.error("`:` after field name"))?;
+					
+					fields.push((field, self.parse_expr()?));
+					self.skip_newlines();
+					
+					match self.peek() {
+						Some(Token::Comma) => {
+							self.next();
+							self.skip_newlines();
+						}
+						Some(Token::RBrace) => {}
+						_ => Err(self.error("`,` or `}` after field in record"))?,
+					}
+				}
+				_ => Err(self.error("field name or `}` in record"))?,
+			}
+		}
+		self.next();
+		
+		Ok(fields)
+	}
+	
+	/// Error for a malformed character or string literal that was just read.
+	fn invalid_char(&self) -> ParseError {
+		self.error_prev(ParseErrorKind::InvalidChar)
+	}
+}
+
 impl Eval for Literal {
-	fn eval(&self, t: &StackFrame) -> EvalResult<Value> {
+	fn eval(&self, t: &StackFrame, m: &Module) -> EvalResult<Value> {
 		Ok(match self {
 			Literal::Nil       => Value::Nil,
 			Literal::Bool(b)   => Value::Bool(*b),
 			Literal::Int(n)    => Value::Int(*n),
 			Literal::UInt(n)   => Value::Uint(*n),
+			Literal::BigInt(n) => Value::BigInt(n.clone()),
 			Literal::Char(c)   => Value::Char(*c),
 			Literal::String(s) => Value::String(s.clone()),
 			
 			Literal::Array(arr) => Value::Array({
 				let mut vec = Vec::with_capacity(arr.len());
 				for expr in arr.iter() {
-					vec.push(expr.eval(t)?);
+					vec.push(expr.eval(t, m)?);
 				}
 				vec.into_boxed_slice()
 			}),
-			Literal::Fn(args, ret) => todo!(),
+			Literal::Fn(params, body) =>
+				Value::Fn(Rc::new(Closure::new(params, body, t))),
+			
+			Literal::Record(name, given) => {
+				let record = m.get_record(name)
+					.ok_or_else(|| EvalError::UnknownRecord(name.clone()))?;
+				let typ = Type::Record(name.clone());
+				
+				if let Some((field, _)) = given.iter()
+					.find(|(field, _)| !record.fields.iter().any(|(name, _)| name == field))
+				{
+					return Err(EvalError::UnknownField { typ, field: field.clone() });
+				}
+				
+				// store the fields in the order they're declared in
+				let mut fields = Vec::with_capacity(record.fields.len());
+				for (field, field_type) in &record.fields {
+					let (_, expr) = given.iter()
+						.find(|(name, _)| name == field)
+						.ok_or_else(|| EvalError::MissingField {
+							typ: typ.clone(),
+							field: field.clone(),
+						})?;
+					fields.push((field.clone(), expr.eval(t, m)?.conform(field_type)?));
+				}
+				Value::Record(name.clone(), fields.into_boxed_slice())
+			}
 		})
 	}
 }
@@ -200,10 +303,12 @@ impl Literal {
 			Literal::Bool(_)   => Some(Type::Bool),
 			Literal::Int(_)    => Some(Type::Int),
 			Literal::UInt(_)   => Some(Type::UInt),
+			Literal::BigInt(_) => Some(Type::BigInt),
 			Literal::Char(_)   => Some(Type::Char),
 			Literal::String(_) => Some(Type::String),
 			Literal::Array(_)  => None,
 			Literal::Fn(..)    => None,
+			Literal::Record(name, _) => Some(Type::Record(name.clone())),
 		}
 	}
 }
@@ -1,6 +1,6 @@
 use super::*;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Deref {
 	Direct,
 	Field(String),
@@ -11,16 +11,26 @@ pub enum Deref {
 pub struct LValue {
 	pub id: String,
 	pub ops: Vec<Deref>,
+	pub span: Span,
+}
+
+impl PartialEq for LValue {
+	fn eq(&self, other: &Self) -> bool {
+		self.id == other.id && self.ops == other.ops
+	}
 }
 
+impl Eq for LValue {}
+
 // TODO ponder: is `var name` and `drop name` within statements part of a bigger pattern?
 impl Parser<'_> {
 	pub fn parse_lval(&mut self) -> ParseResult<LValue> {
 	    let mut ops = Vec::new();
+	    let start = self.peek_span();
 	    
 	    // get lval name
 	    let name = self.expect_ident()
-	    	.ok_or("variable name in left-value expression")?;
+	    	.ok_or_else(|| self.error("variable name in left-value expression"))?;
     	
 	    loop {
 	    	match self.peek() {
@@ -40,7 +50,7 @@ impl Parser<'_> {
 							let expr = self.parse_expr()?;
 							
 							self.expect(Token::RParen)
-								.ok_or("`)` after index expression")?;
+								.ok_or_else(|| self.error("`)` after index expression"))?;
 							
 							ops.push(Deref::Index(expr));
     					}
@@ -48,7 +58,7 @@ impl Parser<'_> {
     						let name = self.expect_ident().unwrap();
 	    					ops.push(Deref::Field(name));
     					}
-    					_ => Err("field name or `(`")?,
+    					_ => Err(self.error("field name or `(`"))?,
     				}
     			}
     			
@@ -56,17 +66,24 @@ impl Parser<'_> {
 			}
 		}
         
-        Ok(LValue { id: name, ops })
+        Ok(LValue { id: name, ops, span: self.span_from(start) })
 	}
 }
 
 impl Eval for LValue {
-	fn eval(&self, t: &StackFrame) -> EvalResult<Value> {
-		let var = t.get(self)?.clone();
+	fn eval(&self, t: &StackFrame, m: &Module) -> EvalResult<Value> {
+		// the name of a procedure is a value too, unless a variable hides it
+		if self.ops.is_empty() && t.lookup(&self.id).is_none() {
+			if let Some(pr) = m.get_proc(&self.id) {
+				return Ok(Value::Proc(pr.name.clone(), pr.signature().into()));
+			}
+		}
+		
+		let var = t.get(self, m)?.clone();
 		
 		/*for op in &self.ops {
 			var = match op {
-				Deref::Index(expr) => match (var, expr.eval(t)?) {
+				Deref::Index(expr) => match (var, expr.eval(t, m)?) {
 					(Value::String(s), Value::Int(i)) =>
 						s.chars().nth(i as usize).unwrap().into(),
 					
@@ -7,40 +7,80 @@ use super::*;
 pub struct Module {
 	pub name: String,
 	pub items: Vec<Item>,
+	pub span: Span,
+	/// Whether `int` and `uint` arithmetic evaluated in this module reports
+	/// overflow as an error instead of wrapping around.
+	pub checked: bool,
 }
 
 impl Module {
 	pub fn new(name: String, items: Vec<Item>) -> Self {
-		Module { name, items }
+		Module { name, items, span: Span::default(), checked: false }
 	}
 	
 	pub fn insert(&mut self, item: Item) {
 		self.items.push(item);
 	}
+	
+	/// Finds a procedure in this module by name.
+	pub fn get_proc(&self, name: &str) -> Option<&Procedure> {
+		self.items.iter()
+			.find_map(|item| match item {
+				Item::Proc(pr) if pr.name == name => Some(pr),
+				_ => None,
+			})
+	}
+	
+	/// Finds a function in this module by name.
+	pub fn get_fn(&self, name: &str) -> Option<&Function> {
+		self.items.iter()
+			.find_map(|item| match item {
+				Item::Fn(f) if f.name == name => Some(f),
+				_ => None,
+			})
+	}
+	
+	/// Finds a record type in this module by name.
+	pub fn get_record(&self, name: &str) -> Option<&Record> {
+		self.items.iter()
+			.find_map(|item| match item {
+				Item::Record(r) if r.name == name => Some(r),
+				_ => None,
+			})
+	}
 }
 
 impl Parser<'_> {
 	pub fn parse_mod(&mut self) -> ParseResult<Module> {
-		self.expect(Token::Mod).ok_or("`mod`")?;
+		let start = self.peek_span();
+		
+		self.expect(Token::Mod).ok_or_else(|| self.error("`mod`"))?;
 		
 		let name = self.expect_ident()
-			.ok_or("module name")?;
+			.ok_or_else(|| self.error("module name"))?;
 		
 		self.expect(Token::Newline)
-			.ok_or("newline after module name")?;
+			.ok_or_else(|| self.error("newline after module name"))?;
 		
 		// parse as many items as possible
 		let mut items = Vec::new();
 		loop {
 			match self.peek() {
 				Some(Token::End) => break,
-				Some(_) => items.push(self.parse_item()?),
-				None => Err("an item or `end`")?,
+				Some(Token::Newline) => { self.next(); }
+				Some(_) => match self.parse_item() {
+					Ok(item) => items.push(item),
+					Err(e) => {
+						self.errors.push(e);
+						self.recover_item();
+					}
+				}
+				None => Err(self.error("an item or `end`"))?,
 			}
 		}
 		self.next();
 		
-		Ok(Module { name, items })
+		Ok(Module { name, items, span: self.span_from(start), checked: false })
 	}
 }
 
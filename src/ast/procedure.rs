@@ -1,15 +1,27 @@
 use super::*;
 
-use crate::interpret::EvalResult;
+use crate::interpret::{EvalError, EvalResult};
 
-#[derive(Clone, Copy, PartialEq, Eq)]
-enum Dir { Fore, Back }
+/// Direction a procedure is run in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dir { Fore, Back }
+
+impl Dir {
+	/// The opposite direction.
+	pub fn invert(self) -> Self {
+		match self {
+			Dir::Fore => Dir::Back,
+			Dir::Back => Dir::Fore,
+		}
+	}
+}
 
 #[derive(Debug, Clone)]
 pub struct Param {
 	pub name: String,
 	pub mutable: bool,
 	pub typ: Type,
+	pub span: Span,
 }
 
 #[derive(Clone)]
@@ -18,8 +30,8 @@ pub enum ProcDef {
 	User(Vec<Stmt>),
 	/// Pair of irreversible functions defining an internal procedure.
 	Internal {
-		fore: fn(&mut [Value]) -> EvalResult<()>,
-		back: fn(&mut [Value]) -> EvalResult<()>,
+		fore: fn(&mut [Value], &Module) -> EvalResult<()>,
+		back: fn(&mut [Value], &Module) -> EvalResult<()>,
 	},
 	External,
 }
@@ -41,16 +53,19 @@ pub struct Procedure {
 	pub params: Vec<Param>,
 	/// How and where a procedure is defined.
 	pub code: ProcDef,
+	pub span: Span,
 }
 
 impl Parser<'_> {
 	pub fn parse_proc(&mut self) -> ParseResult<Procedure> {
-		self.expect(Token::Proc).ok_or("`proc`")?;
+		let start = self.peek_span();
+		
+		self.expect(Token::Proc).ok_or_else(|| self.error("`proc`"))?;
 		
 		let proc_name = self.expect_ident()
-			.ok_or("procedure name")?;
+			.ok_or_else(|| self.error("procedure name"))?;
 		
-		let mut params = Vec::new();
+		let mut params: Vec<Param> = Vec::new();
 		
 		// parse parameter list
 		// starting '('
@@ -63,62 +78,55 @@ impl Parser<'_> {
 					
 					// parse as parameter
 					Some(_) => {
+						let param_start = self.peek_span();
 						let mutable = self.expect(Token::Var).is_some();
 						
 						let param_name = self.expect_ident()
-							.ok_or("parameter name in procedure declaration")?;
+							.ok_or_else(|| self.error("parameter name in procedure declaration"))?;
+						
+						if params.iter().any(|param| param.name == param_name) {
+							return Err(self.error_prev(
+								ParseErrorKind::DuplicateParam(param_name)
+							));
+						}
 						
 						self.expect(Token::Colon)
-							.ok_or("`:` after parameter name")?;
+							.ok_or_else(|| self.error("`:` after parameter name"))?;
 						
 						// get type
 						let typ = self.parse_type()?;
 						
-						for Param { name, .. } in &params {
-							if *name == param_name {
-								eprintln!(
-									"Some parameter names in `proc {}` overlap: {:?}",
-									proc_name, name
-								);
-								Err("parameter names to be unique")?;
-							}
-						}
+						let span = self.span_from(param_start);
 						
-						params.push(Param { mutable, name: param_name, typ });
+						params.push(Param { mutable, name: param_name, typ, span });
 						
 						match self.peek() {
 							Some(Token::Comma) => { self.next(); }
 							Some(Token::RParen) => {}
-							_ => Err("`,` or `)` in parameter list")?
+							_ => Err(self.error("`,` or `)` in parameter list"))?
 						}
 					}
 					
-					None => Err("`,` or `)` in parameter list")?,
+					None => Err(self.error("`,` or `)` in parameter list"))?,
 				}
 			}
 			self.next();
 		}
 		
 		self.expect(Token::Newline)
-			.ok_or("newline after parameter list")?;
+			.ok_or_else(|| self.error("newline after parameter list"))?;
 		
 		self.skip_newlines();
 		
 		// code block section
-		let mut code = Vec::new();
-		loop {
-			match self.peek() {
-				Some(Token::End) => break,
-				Some(_) => code.push(self.parse_stmt()?),
-				None => Err("a statement or `end`")?,
-			}
-		}
+		let code = self.parse_block(&[Token::End], "a statement or `end`")?;
 		self.next();
 		
 		Ok(Procedure {
 			name: proc_name,
 			params,
 			code: ProcDef::User(code),
+			span: self.span_from(start),
 		})
 	}
 }
@@ -127,66 +135,116 @@ impl Parser<'_> {
 use crate::interpret::StackFrame;
 
 impl Procedure {
-	fn call_base(&self, dir: Dir, args: Vec<Value>, m: &Module) -> EvalResult<Vec<Value>> {
+	/// Runs the procedure in the given direction. If `transactional` is set
+	/// and the procedure fails part-way through, every statement it ran is
+	/// undone before the original error is returned.
+	pub fn invoke(&self, dir: Dir, args: Vec<Value>, m: &Module, transactional: bool) -> EvalResult<Vec<Value>> {
+		self.run(dir, args, m, transactional)
+			.map_err(|e| e.in_proc(&self.name, dir))
+	}
+	
+	fn run(&self, dir: Dir, args: Vec<Value>, m: &Module, transactional: bool) -> EvalResult<Vec<Value>> {
 		// verify number of arguments and their types
-		assert_eq!(args.len(), self.params.len(),
-			"wrong number of parameters before calling proc {}", self.name
-		);
-		for (arg, param) in args.iter().zip(&self.params) {
-			assert_eq!(arg.get_type(), param.typ,
-				"value with wrong type passed before calling proc {}", self.name
-			);
+		if args.len() != self.params.len() {
+			return Err(EvalError::ArityMismatch {
+				name: self.name.clone(),
+				expected: self.params.len(),
+				got: args.len(),
+			});
 		}
+		self.check_types(&args)?;
 		
 		// make stack frame with parameter names bound to argument values
+		let initial = args.clone();
 		let mut vars = StackFrame::new(self.params.iter()
 			.map(|param| param.name.clone())
-			.zip(args.clone())
+			.zip(args)
 			.collect()
 		);
 		
+		let mut journal = if transactional {
+			Journal::new()
+		} else {
+			Journal::default()
+		};
+		
+		match self.run_body(dir, &initial, &mut vars, m, &mut journal) {
+			Ok(()) => Ok(vars.into_inner()),
+			Err(e) => match journal.rollback(&mut vars, m) {
+				Ok(()) => Err(e),
+				Err(cause) => Err(e.rollback_failed(cause)),
+			}
+		}
+	}
+	
+	fn run_body(&self, dir: Dir, initial: &[Value], vars: &mut StackFrame, m: &Module, journal: &mut Journal) -> EvalResult<()> {
 		// execute the actual code
 		match (dir, &self.code) {
 			(Dir::Fore, ProcDef::User(code)) => {
 				for stmt in code {
-					stmt.eval(&mut vars, m)?;
+					stmt.eval_recorded(vars, m, journal)?;
 				}
 			}
 			(Dir::Back, ProcDef::User(code)) => {
-				for stmt in code {
-					stmt.clone().invert().eval(&mut vars, m)?;
+				for stmt in code.iter().rev() {
+					stmt.clone().invert().eval_recorded(vars, m, journal)?;
 				}
 			}
 			(Dir::Fore, ProcDef::Internal { fore, .. }) => {
-				fore(vars.values())?;
+				fore(vars.values(), m)?;
 			}
 			(Dir::Back, ProcDef::Internal { back, .. }) => {
-				back(vars.values())?;
+				back(vars.values(), m)?;
 			}
-			_ => todo!()
+			(_, ProcDef::External) =>
+				return Err(EvalError::Unsupported("external procedures")),
 		}
 		
-		let args = dbg![vars.into_inner()];
+		// local variables must all be dropped by the end of the procedure
+		if let Some(name) = vars.names().get(self.params.len()) {
+			return Err(EvalError::UndroppedVar(name.clone()));
+		}
 		
-		// verify number of arguments and their types again
-		assert_eq!(args.len(), self.params.len(),
-			"wrong number of parameters after calling proc {}", self.name
-		);
-		for (arg, param) in args.iter().zip(&self.params) {
-			assert_eq!(arg.get_type(), param.typ,
-				"value with wrong type received after calling proc {}", self.name
-			);
+		let args = &vars.values()[..];
+		self.check_types(args)?;
+		
+		// catch modifications the static checks couldn't see, e.g. by
+		// internal procedures
+		for ((before, after), param) in initial.iter().zip(args).zip(&self.params) {
+			if !param.mutable && before != after {
+				return Err(EvalError::ReadOnlyModified(param.name.clone()));
+			}
 		}
 		
-		Ok(args)
+		Ok(())
+	}
+	
+	fn check_types(&self, args: &[Value]) -> EvalResult<()> {
+		for (arg, param) in args.iter().zip(&self.params) {
+			let typ = arg.get_type();
+			if param.typ != Type::Infer && typ != param.typ {
+				return Err(EvalError::TypeMismatch {
+					expected: param.typ.clone(),
+					got: typ,
+				});
+			}
+		}
+		Ok(())
+	}
+	
+	/// Whether each parameter is `var`, and its type, as in a `proc` type.
+	pub fn signature(&self) -> Vec<(bool, Type)> {
+		self.params.iter()
+			.map(|param| (param.mutable, param.typ.clone()))
+			.collect()
 	}
 	
 	pub fn call(&self, args: Vec<Value>, m: &Module) -> EvalResult<Vec<Value>> {
-		self.call_base(Dir::Fore, args, m)
+		self.invoke(Dir::Fore, args, m, false)
 	}
 	
 	pub fn uncall(&self, args: Vec<Value>, m: &Module) -> EvalResult<Vec<Value>> {
-		self.call_base(Dir::Back, args, m)
+		self.invoke(Dir::Back, args, m, false)
 	}
 	/*
 	// add the procedure to the scope
@@ -0,0 +1,56 @@
+use super::*;
+
+/// A record type, declared with `struct`, whose values hold a value for each
+/// of its fields.
+#[derive(Debug, Clone)]
+pub struct Record {
+	pub name: String,
+	pub fields: Vec<(String, Type)>,
+	pub span: Span,
+}
+
+impl Parser<'_> {
+	pub fn parse_record(&mut self) -> ParseResult<Record> {
+		let start = self.peek_span();
+		
+		self.expect(Token::Struct).ok_or_else(|| self.error("`struct`"))?;
+		
+		let name = self.expect_ident()
+			.ok_or_else(|| self.error("record name"))?;
+		
+		self.expect(Token::Newline)
+			.ok_or_else(|| self.error("newline after record name"))?;
+		
+		self.skip_newlines();
+		
+		// one field per line
+		let mut fields: Vec<(String, Type)> = Vec::new();
+		loop {
+			match self.peek() {
+				Some(Token::End) => break,
+				Some(Token::Ident) => {
+					let field = self.expect_ident().unwrap();
+					
+					if fields.iter().any(|(name, _)| *name == field) {
+						return Err(self.error_prev(ParseErrorKind::DuplicateField(field)));
+					}
+					
+					self.expect(Token::Colon)
+						.ok_or_else(|| self.error("`:` after field name"))?;
+					
+					let typ = self.parse_type()?;
+					fields.push((field, typ));
+					
+					self.expect(Token::Newline)
+						.ok_or_else(|| self.error("newline after field"))?;
+					
+					self.skip_newlines();
+				}
+				_ => Err(self.error("a field or `end`"))?,
+			}
+		}
+		self.next();
+		
+		Ok(Record { name, fields, span: self.span_from(start) })
+	}
+}
@@ -1,7 +1,15 @@
+use std::convert::TryFrom;
+
 use super::*;
 
 #[derive(Debug, Clone)]
-pub enum Stmt {
+pub struct Stmt {
+	pub kind: StmtKind,
+	pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub enum StmtKind {
 	Skip,
 	
 	//Not(LValue),
@@ -17,60 +25,254 @@ pub enum Stmt {
 	Swap(LValue, LValue),
 	//CSwap(Factor, LValue, LValue),
 	
-	Do(String, Vec<Expr>),
-	Undo(String, Vec<Expr>),
+	Do(String, Vec<Arg>),
+	Undo(String, Vec<Arg>),
 	
 	Var(String, Type, Expr, Vec<Stmt>, Expr),
 	If(Expr, Vec<Stmt>, Vec<Stmt>, Expr),
 	From(Expr, Vec<Stmt>, Vec<Stmt>, Expr),
-	//FromVar(String, Expr, Vec<Stmt>, Vec<Stmt>, Expr),
-	//Match(String, Vec<_, Vec<Stmt>>),
-	//For(String, Expr, Vec<Stmt>),
+	/// A `from` loop with a counter that's declared on entry and dropped on
+	/// exit.
+	FromVar(String, Type, Expr, Vec<Stmt>, Vec<Stmt>, Expr),
+	Match(Vec<Arm>),
+	/// Runs the block once for each step, in ascending order for `Dir::Fore`.
+	For(String, Steps, Vec<Stmt>, Dir),
+}
+
+/// What a `for` loop steps through.
+#[derive(Debug, Clone)]
+pub enum Steps {
+	/// The integers from the first bound up to, but not including, the
+	/// second.
+	Range(Expr, Expr),
+	/// The elements of an array.
+	Each(Expr),
+}
+
+/// One arm of a `match` statement.
+///
+/// A missing guard or exit assertion holds exactly when those of all the other
+/// arms don't, like an `else` branch.
+#[derive(Debug, Clone)]
+pub struct Arm {
+	pub guard: Option<Expr>,
+	pub block: Vec<Stmt>,
+	pub assert: Option<Expr>,
+}
+
+/// An argument in a procedure call.
+#[derive(Debug, Clone)]
+pub enum Arg {
+	Expr(Expr),
+	/// `var name`: declares a zero-initialised variable for the callee to
+	/// fill in. It stays live after the call.
+	Var(String),
+	/// `drop name`: passes a variable that the callee must clear, and removes
+	/// it after the call.
+	Drop(String),
+}
+
+impl Arg {
+	pub fn invert(self) -> Self {
+		match self {
+			Arg::Var(name) => Arg::Drop(name),
+			Arg::Drop(name) => Arg::Var(name),
+			arg => arg,
+		}
+	}
+}
+
+/// Inverts a block of statements, so that running it undoes the original.
+pub fn invert_block(block: Vec<Stmt>) -> Vec<Stmt> {
+	block.into_iter()
+		.rev()
+		.map(Stmt::invert)
+		.collect()
 }
 
 impl Stmt {
+	pub fn invert(self) -> Self {
+		Stmt { kind: self.kind.invert(), span: self.span }
+	}
+}
+
+impl StmtKind {
 	pub fn invert(self) -> Self {
 		match self {
-			Stmt::Skip     => self,
-			Stmt::Xor(..)  => self,
-			Stmt::Swap(..) => self,
+			StmtKind::Skip     => self,
+			StmtKind::Xor(..)  => self,
+			StmtKind::Swap(..) => self,
 			
-			Stmt::RotLeft(l, v) => Stmt::RotRight(l, v),
-			Stmt::RotRight(l, v) => Stmt::RotLeft(l, v),
+			StmtKind::RotLeft(l, v) => StmtKind::RotRight(l, v),
+			StmtKind::RotRight(l, v) => StmtKind::RotLeft(l, v),
 			
-			Stmt::Add(l, v) => Stmt::Sub(l, v),
-			Stmt::Sub(l, v) => Stmt::Add(l, v),
+			StmtKind::Add(l, v) => StmtKind::Sub(l, v),
+			StmtKind::Sub(l, v) => StmtKind::Add(l, v),
 			
-			Stmt::Do(p, args) => Stmt::Undo(p, args),
-			Stmt::Undo(p, args) => Stmt::Do(p, args),
+			StmtKind::Do(p, args) =>
+				StmtKind::Undo(p, args.into_iter().map(Arg::invert).collect()),
+			StmtKind::Undo(p, args) =>
+				StmtKind::Do(p, args.into_iter().map(Arg::invert).collect()),
 			
-			Stmt::Var(n, t, init, s, dest) =>
-				Stmt::Var(n, t, dest, s, init),
-			Stmt::If(test, b, eb, assert) =>
-				Stmt::If(assert, b, eb, test),
-			Stmt::From(assert, b, lb, test) =>
-				Stmt::From(test, b, lb, assert),
+			StmtKind::Var(n, t, init, s, dest) =>
+				StmtKind::Var(n, t, dest, invert_block(s), init),
+			StmtKind::If(test, b, eb, assert) =>
+				StmtKind::If(assert, invert_block(b), invert_block(eb), test),
+			StmtKind::From(assert, b, lb, test) =>
+				StmtKind::From(test, invert_block(b), invert_block(lb), assert),
+			StmtKind::FromVar(n, t, init, b, lb, dest) =>
+				StmtKind::FromVar(n, t, dest, invert_block(b), invert_block(lb), init),
+			StmtKind::Match(arms) =>
+				StmtKind::Match(arms.into_iter()
+					.map(|arm| Arm {
+						guard: arm.assert,
+						block: invert_block(arm.block),
+						assert: arm.guard,
+					})
+					.collect()
+				),
+			StmtKind::For(name, steps, block, dir) =>
+				StmtKind::For(name, steps, invert_block(block), dir.invert()),
 		}
 	}
 }
 
+/// Whether a statement starting with `token` has a block of statements,
+/// closed by a line starting with a token for which `closes_block` holds.
+fn opens_block(token: Token) -> bool {
+	matches!(token, Token::If | Token::From | Token::For | Token::Match | Token::Var)
+}
+
+fn closes_block(token: Token) -> bool {
+	matches!(token, Token::Fi | Token::Loop | Token::End | Token::Drop)
+}
+
 impl Parser<'_> {
+	/// Parses statements until one of the `terminators` is next, without
+	/// consuming it.
+	///
+	/// A statement with a syntax error is recorded in `self.errors` and
+	/// skipped, so that the rest of the block can still be checked. Reaching
+	/// the end of the file is not recoverable, and is reported as `eof_msg`.
+	pub fn parse_block(&mut self, terminators: &[Token], eof_msg: &'static str) -> ParseResult<Vec<Stmt>> {
+		let mut block = Vec::new();
+		
+		loop {
+			match self.peek() {
+				Some(token) if terminators.contains(token) => break,
+				Some(&first) => {
+					let start = self.peek_span();
+					match self.parse_stmt() {
+						Ok(stmt) => block.push(stmt),
+						Err(e) => {
+							self.errors.push(e);
+							self.recover_stmt(first, start);
+						}
+					}
+				}
+				None => Err(self.error(eof_msg))?,
+			}
+		}
+		
+		Ok(block)
+	}
+	
+	/// Skips ahead to the next statement boundary after a syntax error: past
+	/// the end of the current line, or up to a keyword that closes a block.
+	///
+	/// If the statement starting at `start` failed on its very first token,
+	/// the whole line is skipped so that the parser always makes progress. If
+	/// it opens a block and failed on its first line, the rest of the block is
+	/// skipped too, so that its closing line isn't reported again.
+	fn recover_stmt(&mut self, first: Token, start: Span) {
+		let stuck = self.peek_span() == start;
+		let in_header = !self.tokens.source()[start.start..self.peek_span().start].contains('\n');
+		
+		loop {
+			match self.peek() {
+				None => break,
+				Some(Token::Newline) => {
+					self.skip_newlines();
+					break;
+				}
+				Some(Token::End)
+				| Some(Token::Fi)
+				| Some(Token::Loop)
+				| Some(Token::Drop)
+				| Some(Token::Until)
+				| Some(Token::When)
+				| Some(Token::Else) if !stuck => break,
+				Some(_) => { self.next(); }
+			}
+		}
+		
+		if !stuck && in_header && opens_block(first) {
+			self.skip_block();
+		}
+	}
+	
+	/// Skips lines up to and including the one that closes a block whose
+	/// first line has already been skipped. Nested blocks are tracked by the
+	/// keyword each line starts with.
+	fn skip_block(&mut self) {
+		let mut depth = 1;
+		while let Some(&token) = self.peek() {
+			if opens_block(token) {
+				depth += 1;
+			} else if closes_block(token) {
+				depth -= 1;
+			}
+			
+			while !matches!(self.peek(), None | Some(Token::Newline)) {
+				self.next();
+			}
+			self.skip_newlines();
+			
+			if depth == 0 {
+				break;
+			}
+		}
+	}
+	
+	/// Parses a call argument, which is either an expression or a `var` or
+	/// `drop` sub-statement.
+	pub fn parse_arg(&mut self) -> ParseResult<Arg> {
+		if self.expect(Token::Var).is_some() {
+			let name = self.expect_ident()
+				.ok_or_else(|| self.error("variable name after `var`"))?;
+			Ok(Arg::Var(name))
+		} else if self.expect(Token::Drop).is_some() {
+			let name = self.expect_ident()
+				.ok_or_else(|| self.error("variable name after `drop`"))?;
+			Ok(Arg::Drop(name))
+		} else {
+			Ok(Arg::Expr(self.parse_expr()?))
+		}
+	}
+	
 	pub fn parse_stmt(&mut self) -> ParseResult<Stmt> {
-		let stmt = match *self.peek().ok_or("a statement")? {
+		let start = self.peek_span();
+		
+		let token = match self.peek() {
+			Some(&token) => token,
+			None => Err(self.error("a statement"))?,
+		};
+		
+		let kind = match token {
 			// skip
 			// TODO use this keyword as a prefix to comment out statements?
 			Token::Skip => {
 				self.next();
-				Stmt::Skip
+				StmtKind::Skip
 			}
 			
 			/* do-call and undo-call syntax accept three forms:
 			   + `do something`
-			   + `do something: some, args` (1 arg min) TODO
+			   + `do something: some, args` (1 arg min)
 			   + `do something(
 			          multiline,
-			          args
-			      )` (0 arg min) TODO
+			          args,
+			      )` (0 arg min)
 			   also has special syntax like:
 			   + do something: var new_var, drop used_var
 			*/
@@ -78,21 +280,16 @@ impl Parser<'_> {
 				self.next();
 				
 				let name = self.expect_ident()
-					.ok_or(match kw {
+					.ok_or_else(|| self.error(match kw {
 						Token::Do => "procedure name after `do`",
 						Token::Undo => "procedure name after `undo`",
 						_ => unreachable!()
-					})?;
+					}))?;
 				
-				// TODO check for parentheses. if so, go into multiline mode
 				let mut args = Vec::new();
 				
-				if self.expect(Token::Newline).is_some() {
-					// do nothing
-				} else if self.expect(Token::Colon).is_some() {
-					// TODO check for newline, in case expression is missing
-					let expr = self.parse_expr()?;
-					args.push(expr);
+				if self.expect(Token::Colon).is_some() {
+					args.push(self.parse_arg()?);
 					
 					loop {
 						match self.peek() {
@@ -101,22 +298,48 @@ impl Parser<'_> {
 								break,
 							Some(Token::Comma) => {
 								self.next();
-								// TODO check for "substatements" first.
-								// E.g. `var file` or `drop buf` in args.
-								args.push(self.parse_expr()?);
+								args.push(self.parse_arg()?);
 							}
-							_ => Err("`,` or newline")?,
+							_ => Err(self.error("`,` or newline"))?,
 						}
 					}
 				} else if self.expect(Token::LParen).is_some() {
-					unimplemented!();
+					// multiline mode; newlines may appear anywhere between
+					// arguments, and a trailing comma is allowed.
+					self.skip_newlines();
+					
+					loop {
+						match self.peek() {
+							Some(Token::RParen) => break,
+							Some(_) => {
+								args.push(self.parse_arg()?);
+								self.skip_newlines();
+								
+								match self.peek() {
+									Some(Token::Comma) => {
+										self.next();
+										self.skip_newlines();
+									}
+									Some(Token::RParen) => {}
+									_ => Err(self.error("`,` or `)` after argument"))?,
+								}
+							}
+							None => Err(self.error("an argument or `)`"))?,
+						}
+					}
+					self.next();
 				} else {
-					Err("`:`, or newline")?;
-				};
+					// `do something` with no arguments; the newline is left
+					// for the end of the statement.
+					match self.peek() {
+						Some(Token::Newline) | None => {}
+						_ => Err(self.error("`:`, `(`, or newline"))?,
+					}
+				}
 				
 				match kw {
-					Token::Do   => Stmt::Do(name, args),
-					Token::Undo => Stmt::Undo(name, args),
+					Token::Do   => StmtKind::Do(name, args),
+					Token::Undo => StmtKind::Undo(name, args),
 					_ => unreachable!()
 				}
 			}
@@ -125,46 +348,74 @@ impl Parser<'_> {
 			Token::From => {
 				self.next();
 				
-				// parse loop assertion
+				// `from var i := a`, which declares a counter for the loop
+				let counter = match self.expect(Token::Var) {
+					Some(_) => {
+						let name = self.expect_ident()
+							.ok_or_else(|| self.error("name of loop counter after `var`"))?;
+						
+						let typ = match self.expect(Token::Colon) {
+							Some(_) => self.parse_type()?,
+							None => Type::Infer,
+						};
+						
+						self.expect(Token::Assign)
+							.ok_or_else(|| self.error("`:=` in loop counter declaration"))?;
+						
+						Some((name, typ))
+					}
+					None => None,
+				};
+				
+				// parse loop assertion, or the counter's initial value
 				let assert = self.parse_expr()?;
 				
 				self.expect(Token::Newline)
-					.ok_or("newline after `from` assertion")?;
+					.ok_or_else(|| self.error("newline after `from` assertion"))?;
 				
 				// eat empty lines
 				self.skip_newlines();
 				
 				// parse the main loop block
-				let mut main_block = Vec::new();
-				loop {
-					match self.peek() {
-						Some(Token::Until) => break,
-						Some(_) => main_block.push(self.parse_stmt()?),
-						None => Err("a statement or `until`")?,
+				let main_block = self.parse_block(&[Token::Until], "a statement or `until`")?;
+				self.next();
+				
+				// `until drop i := b` to finish a loop with a counter
+				if let Some((name, _)) = &counter {
+					self.expect(Token::Drop)
+						.ok_or_else(|| self.error("`drop` of loop counter after `until`"))?;
+					
+					let drop_name = self.expect_ident()
+						.ok_or_else(|| self.error("name after `drop`"))?;
+					
+					if drop_name != *name {
+						return Err(self.error_prev(
+							ParseErrorKind::Expected("same variable name as in `from`")
+						));
 					}
+					
+					self.expect(Token::Assign)
+						.ok_or_else(|| self.error("`:=` and final value of loop counter"))?;
 				}
-				self.next();
 				
-				// parse the `until` test expression
+				// parse the `until` test expression, or the counter's final value
 				let test = self.parse_expr()?;
 				
 				self.expect(Token::Newline)
-					.ok_or("newline after `until` expression")?;
+					.ok_or_else(|| self.error("newline after `until` expression"))?;
 				
 				self.skip_newlines();
 				
 				// parse reverse loop block
-				let mut back_block = Vec::new();
-				loop {
-					match self.peek() {
-						Some(Token::Loop) => break,
-						Some(_) => back_block.push(self.parse_stmt()?),
-						None => Err("a statement or `loop`")?,
-					}
-				}
+				let back_block = self.parse_block(&[Token::Loop], "a statement or `loop`")?;
 				self.next();
 				
-				Stmt::From(assert, main_block, back_block, test)
+				match counter {
+					Some((name, typ)) =>
+						StmtKind::FromVar(name, typ, assert, main_block, back_block, test),
+					None =>
+						StmtKind::From(assert, main_block, back_block, test),
+				}
 			}
 			
 			// var-drop
@@ -173,7 +424,7 @@ impl Parser<'_> {
 				
 				// get name
 				let name = self.expect_ident()
-					.ok_or("name in variable declaration")?;
+					.ok_or_else(|| self.error("name in variable declaration"))?;
 				
 				// get optional type
 				let typ = match self.expect(Token::Colon) {
@@ -183,34 +434,29 @@ impl Parser<'_> {
 				
 				// check for assignment op
 				self.expect(Token::Assign)
-					.ok_or("`:=` in variable declaration")?;
+					.ok_or_else(|| self.error("`:=` in variable declaration"))?;
 				
 				// get initialization expression
 				let init = self.parse_expr()?;
 				
 				self.expect(Token::Newline)
-					.ok_or("newline after variable declaration")?;
+					.ok_or_else(|| self.error("newline after variable declaration"))?;
 				
 				// eat empty lines
 				self.skip_newlines();
 				
 				// get list of statements for which this variable is valid
-				let mut block = Vec::new();
-				loop {
-					match self.peek() {
-						Some(Token::Drop) => break,
-						Some(_) => block.push(self.parse_stmt()?),
-						None => Err("a statement or `drop`")?,
-					}
-				}
+				let block = self.parse_block(&[Token::Drop], "a statement or `drop`")?;
 				self.next();
 				
 				// assert name
 				let drop_name = self.expect_ident()
-					.ok_or("name after `drop`")?;
+					.ok_or_else(|| self.error("name after `drop`"))?;
 				
 				if drop_name != name {
-					Err("same variable name as before")?;
+					return Err(self.error_prev(
+						ParseErrorKind::Expected("same variable name as before")
+					));
 				}
 				
 				// get optional deinit value
@@ -219,7 +465,7 @@ impl Parser<'_> {
 					None => init.clone(),
 				};
 				
-				Stmt::Var(name, typ, init, block, drop)
+				StmtKind::Var(name, typ, init, block, drop)
 			}
 			
 			// if-else
@@ -230,19 +476,13 @@ impl Parser<'_> {
 				let cond = self.parse_expr()?;
 				
 				self.expect(Token::Newline)
-					.ok_or("newline after `if` predicate")?;
+					.ok_or_else(|| self.error("newline after `if` predicate"))?;
 				
 				// parse the main block
-				let mut main_block = Vec::new();
-				loop {
-					match self.peek() {
-						Some(Token::Else)
-						| Some(Token::Fi) => break,
-						Some(_) => main_block.push(self.parse_stmt()?),
-						None => Err("a statement, `else`, or `fi`")?,
-					}
-				}
-				//self.next();
+				let main_block = self.parse_block(
+					&[Token::Else, Token::Fi],
+					"a statement, `else`, or `fi`",
+				)?;
 				
 				// parse else section
 				let mut else_block = Vec::new();
@@ -251,270 +491,476 @@ impl Parser<'_> {
 				if self.expect(Token::Else).is_some() {
 					if self.expect(Token::Newline).is_some() {
 						// parse a block
-						loop {
-							match self.peek() {
-								Some(Token::Fi) => break,
-								Some(_) => else_block.push(self.parse_stmt()?),
-								None => Err("a statement or `fi`")?,
-							}
-						}
+						else_block = self.parse_block(&[Token::Fi], "a statement or `fi`")?;
 					} else if self.peek() == Some(&Token::If) {
 						// check if immediately followed by an `if` token.
 						// allows "embedding" of chained `if` statements.
 						else_block.push(self.parse_stmt()?);
 					} else {
-						Err("chaining `if` or a newline")?;
+						Err(self.error("chaining `if` or a newline"))?;
 					}
 				}
 				
 				// expect ending `fi`
 				let fi = self.expect(Token::Fi)
-					.ok_or("`fi` to finish `if` statement")?;
+					.ok_or_else(|| self.error("`fi` to finish `if` statement"))?;
 				
 				// parse `fi` assertion, if any
 				let assert = match self.peek() {
 					Some(Token::Newline) => cond.clone(),
 					Some(_) => self.parse_expr()?,
-					None => Err("a newline or expression after `fi`")?,
+					None => Err(self.error("a newline or expression after `fi`"))?,
+				};
+				
+				StmtKind::If(cond, main_block, else_block, assert)
+			}
+			
+			// match-when
+			Token::Match => {
+				self.next();
+				
+				// an optional subject that each arm's patterns are compared to
+				let subject = match self.peek() {
+					Some(Token::Newline) => None,
+					_ => Some(self.parse_expr()?),
+				};
+				
+				self.expect(Token::Newline)
+					.ok_or_else(|| self.error("newline after `match`"))?;
+				
+				self.skip_newlines();
+				
+				let mut arms = Vec::new();
+				loop {
+					let guard = match self.peek() {
+						Some(Token::When) => {
+							self.next();
+							Some(self.parse_match_cond(&subject)?)
+						}
+						Some(Token::Else) if !arms.is_empty() => {
+							self.next();
+							None
+						}
+						Some(Token::End) if !arms.is_empty() => break,
+						_ => Err(self.error("`when`, `else`, or `end` in `match` statement"))?,
+					};
+					
+					// parse exit assertion, if any
+					let assert = match self.expect(Token::Then) {
+						Some(_) => Some(self.parse_match_cond(&subject)?),
+						None => guard.clone(),
+					};
+					
+					self.expect(Token::Newline)
+						.ok_or_else(|| self.error("newline after `match` arm"))?;
+					
+					self.skip_newlines();
+					
+					let block = match guard {
+						// `else` has to be the last arm
+						None => self.parse_block(&[Token::End], "a statement or `end`")?,
+						Some(_) => self.parse_block(
+							&[Token::When, Token::Else, Token::End],
+							"a statement, `when`, `else`, or `end`",
+						)?,
+					};
+					
+					arms.push(Arm { guard, block, assert });
+				}
+				self.next();
+				
+				StmtKind::Match(arms)
+			}
+			
+			// for-in
+			Token::For => {
+				self.next();
+				
+				let name = self.expect_ident()
+					.ok_or_else(|| self.error("loop variable name after `for`"))?;
+				
+				self.expect(Token::In)
+					.ok_or_else(|| self.error("`in` after loop variable"))?;
+				
+				let first = self.parse_expr()?;
+				let steps = match self.expect(Token::Range) {
+					Some(_) => Steps::Range(first, self.parse_expr()?),
+					None => Steps::Each(first),
 				};
 				
-				Stmt::If(cond, main_block, else_block, assert)
+				self.expect(Token::Newline)
+					.ok_or_else(|| self.error("`..` or newline after `for` loop bounds"))?;
+				
+				self.skip_newlines();
+				
+				let block = self.parse_block(&[Token::Loop], "a statement or `loop`")?;
+				self.next();
+				
+				StmtKind::For(name, steps, block, Dir::Fore)
 			}
 			
 			Token::Ident => {
 				let lval = self.parse_lval()?;
 				
-				match self.peek().ok_or("modifying operator")? {
+				match self.peek().copied().ok_or_else(|| self.error("modifying operator"))? {
 					Token::Assign => {
 						self.next();
 						let expr = self.parse_expr()?;
-					    Stmt::Xor(lval, expr)
+					    StmtKind::Xor(lval, expr)
 					}
 					Token::AddAssign => {
 						self.next();
 						let expr = self.parse_expr()?;
-					    Stmt::Add(lval, expr)
+					    StmtKind::Add(lval, expr)
 					}
 					Token::SubAssign => {
 						self.next();
 						let expr = self.parse_expr()?;
-					    Stmt::Sub(lval, expr)
+					    StmtKind::Sub(lval, expr)
 					}
 					
 					Token::Rol => {
 						self.next();
 						let expr = self.parse_expr()?;
-					    Stmt::RotLeft(lval, expr)
+					    StmtKind::RotLeft(lval, expr)
 					}
 					Token::Ror => {
 						self.next();
 						let expr = self.parse_expr()?;
-					    Stmt::RotRight(lval, expr)
+					    StmtKind::RotRight(lval, expr)
 					}
 					
 					Token::Swap => {
 						self.next();
 						let rhs = self.parse_lval()?;
-					    Stmt::Swap(lval, rhs)
+					    StmtKind::Swap(lval, rhs)
 					}
 					
-					_ => Err("`:=`, `+=`, `-=`, or `<>`")?,
+					_ => Err(self.error("`:=`, `+=`, `-=`, or `<>`"))?,
 				}
 			}
 			
 			// TODO: handle newline here for empty statement
-			token => {
-				eprintln!("Got {:?}: {}", token, self.slice());
-				Err("a valid statement")?
-			}
+			_ => Err(self.error("a valid statement"))?,
 		};
 				
+		let stmt = Stmt { kind, span: self.span_from(start) };
+		
 		// mandatory newline after statement
 		self.expect(Token::Newline)
-			.ok_or("newline after statement")?;
+			.ok_or_else(|| self.error("newline after statement"))?;
 		
 		// eat all extra newlines
 		self.skip_newlines();
 		
 		Ok(stmt)
 	}
+	
+	/// Parses a `when` guard or `then` assertion. With a subject, it's a value
+	/// that the subject must be equal to.
+	fn parse_match_cond(&mut self, subject: &Option<Expr>) -> ParseResult<Expr> {
+		let expr = self.parse_expr()?;
+		Ok(match subject {
+			Some(subject) => {
+				let span = expr.span;
+				let kind = ExprKind::BinOp(Box::new(subject.clone()), BinOp::Eq, Box::new(expr));
+				Expr::new(kind, span)
+			}
+			None => expr,
+		})
+	}
+}
+
+/// Statements run so far by a transactional call, so that they can be undone
+/// if a later one fails.
+#[derive(Debug, Default)]
+pub struct Journal {
+	/// `None` if nothing is being recorded.
+	entries: Option<Vec<Entry>>,
+}
+
+#[derive(Debug)]
+enum Entry {
+	/// A statement that ran to completion.
+	Ran(Box<Stmt>),
+	/// A `var` statement whose block is still running.
+	Declared(String),
+	/// A variable that went out of scope before the statement declaring it
+	/// finished, like the variable of a `for` loop after each step.
+	Dropped(String, Value),
+}
+
+impl Journal {
+	/// Creates a journal that records statements. `Journal::default()`
+	/// records nothing.
+	pub fn new() -> Self {
+		Journal { entries: Some(Vec::new()) }
+	}
+	
+	pub fn is_recording(&self) -> bool {
+		self.entries.is_some()
+	}
+	
+	fn len(&self) -> usize {
+		self.entries.as_ref().map_or(0, Vec::len)
+	}
+	
+	fn declare(&mut self, name: &str) {
+		if let Some(entries) = &mut self.entries {
+			entries.push(Entry::Declared(name.to_string()));
+		}
+	}
+	
+	fn drop(&mut self, name: &str, val: &Value) {
+		if let Some(entries) = &mut self.entries {
+			entries.push(Entry::Dropped(name.to_string(), val.clone()));
+		}
+	}
+	
+	/// Records that `stmt` completed, replacing the entries of any statements
+	/// nested in it, which started at position `start`.
+	fn commit(&mut self, start: usize, stmt: &Stmt) {
+		if let Some(entries) = &mut self.entries {
+			entries.truncate(start);
+			entries.push(Entry::Ran(Box::new(stmt.clone())));
+		}
+	}
+	
+	/// Undoes everything recorded, most recent first.
+	pub fn rollback(self, t: &mut StackFrame, m: &Module) -> EvalResult<()> {
+		for entry in self.entries.into_iter().flatten().rev() {
+			match entry {
+				Entry::Ran(stmt) => stmt.invert().eval(t, m)?,
+				Entry::Declared(name) => { t.remove(&name)?; }
+				Entry::Dropped(name, val) => t.push(name, val),
+			}
+		}
+		Ok(())
+	}
 }
 
 impl Stmt {
 	pub fn eval(&self, t: &mut StackFrame, m: &Module) -> EvalResult<()> {
+		self.eval_recorded(t, m, &mut Journal::default())
+	}
+	
+	/// Runs the statement, recording it in `journal` if it completes. Calls
+	/// made while the journal is recording are transactional too.
+	pub fn eval_recorded(&self, t: &mut StackFrame, m: &Module, journal: &mut Journal) -> EvalResult<()> {
+		let start = journal.len();
+		self.kind.eval(t, m, journal)
+			.map_err(|e| e.at(self.span))?;
+		journal.commit(start, self);
+		Ok(())
+	}
+}
+
+impl StmtKind {
+	fn eval(&self, t: &mut StackFrame, m: &Module, journal: &mut Journal) -> EvalResult<()> {
 		match self {
-			Stmt::Skip => {}
+			StmtKind::Skip => {}
 			
-			Stmt::Var(id, _, init, block, dest) => {
-				let init = init.eval(t)?;
+			StmtKind::Var(id, typ, init, block, dest) => {
+				let init = init.eval(t, m)?.coerce(typ)?;
+				if *typ != Type::Infer && init.get_type() != *typ {
+					return Err(EvalError::TypeMismatch {
+						expected: typ.clone(),
+						got: init.get_type(),
+					});
+				}
 				t.push(id.clone(), init);
+				journal.declare(id);
 				
 				for stmt in block {
-					if let Err(e) = stmt.eval(t, m) {
-						eprintln!("{:?}", stmt);
-						panic!("var {}: {:?}", id, e);
-					}
+					stmt.eval_recorded(t, m, journal)?;
 				}
 				
-				let (final_id, final_val) = t.pop().unwrap();
+				let final_val = pop_var(t, id)?;
 				
-				assert_eq!(*id, final_id);
-				assert_eq!(final_val, dest.eval(t)?,
-					"variable {:?} had unexpected value", id);
+				// the final value is compared as the variable's own type
+				let expected = match dest.eval(t, m).and_then(|v| v.coerce(&final_val.get_type())) {
+					Ok(expected) => expected,
+					Err(e) => {
+						t.push(id.clone(), final_val);
+						return Err(e);
+					}
+				};
+				if final_val != expected {
+					t.push(id.clone(), final_val.clone());
+					return Err(EvalError::DropMismatch {
+						name: id.clone(),
+						expected,
+						got: final_val,
+					});
+				}
 			}
 			
-			Stmt::Xor(lval, expr) => {
-				let expr = expr.eval(t)?;
-				match (t.get_mut(&lval)?, &expr) {
-					(Value::Int(l), Value::Int(r)) =>
-						*l ^= *r,
-					_ => panic!("tried to do something illegal")
-				}
+			StmtKind::Xor(lval, expr) => {
+				let expr = expr.eval(t, m)?;
+				t.update(lval, m, |val| val.xor(&expr))?;
 			}
 			
-			Stmt::Add(lval, expr) => {
-				let expr = expr.eval(t)?;
-				match (t.get_mut(&lval).unwrap(), &expr) {
-					(Value::Int(ref mut l), Value::Int(r)) =>
-						*l = l.wrapping_add(*r),
-					(l, r) => panic!(
-						"tried to increment a {:?} with a {:?}",
-						l, r
-					)
-				}
+			StmtKind::Add(lval, expr) => {
+				let expr = expr.eval(t, m)?;
+				t.update(lval, m, |val| val.add(&expr, m.checked))?;
 			}
 			
-			Stmt::Sub(lval, expr) => {
-				let expr = expr.eval(t)?;
-				match (t.get_mut(&lval)?, &expr) {
-					(Value::Int(l), Value::Int(r)) =>
-						*l = l.wrapping_sub(*r),
-					_ => panic!("tried to do something illegal")
-				}
+			StmtKind::Sub(lval, expr) => {
+				let expr = expr.eval(t, m)?;
+				t.update(lval, m, |val| val.sub(&expr, m.checked))?;
 			}
 			
-			Stmt::RotLeft(lval, expr) => {
-				let expr = expr.eval(t)?;
-				match (t.get_mut(&lval)?, &expr) {
-					(Value::Int(l), Value::Int(r)) =>
-						*l = l.rotate_left(*r as u32),
-					_ => panic!("tried to do something illegal")
-				}
+			StmtKind::RotLeft(lval, expr) => {
+				let expr = expr.eval(t, m)?;
+				t.update(lval, m, |val| val.rotate_left(&expr))?;
 			}
 			
-			Stmt::RotRight(lval, expr) => {
-				let expr = expr.eval(t)?;
-				match (t.get_mut(&lval)?, &expr) {
-					(Value::Int(l), Value::Int(r)) =>
-						*l = l.rotate_right(*r as u32),
-					_ => panic!("tried to do something illegal")
-				}
+			StmtKind::RotRight(lval, expr) => {
+				let expr = expr.eval(t, m)?;
+				t.update(lval, m, |val| val.rotate_right(&expr))?;
 			}
 			
-			// sighhhhhhhhhhhhhhhhh
-			Stmt::Swap(left, right) => {
-				todo!("swapping is not currently supported");
-				/*
-				// ensure types are the same
-				assert_eq!(
-					t.vars[left_idx].1.get_type(),
-					t.vars[right_idx].1.get_type(),
-					"tried to swap variables with different types"
-				);
-				
-				// get names of values being swapped for later
-				let left_name = t.vars[left_idx].clone();
-				let right_name = t.vars[right_idx].clone();
-				
-				t.vars.swap(left_idx, right_idx);
-				
-				// rectify names
-				t.vars[left_idx] = left_name;
-				t.vars[right_idx] = right_name;
-				*/
+			StmtKind::Swap(left, right) => {
+				// find both places before changing anything, so that e.g.
+				// `i <> a.(i)` uses the original value of `i` for both
+				let left = t.resolve(left, m)?;
+				let right = t.resolve(right, m)?;
+				
+				let mut left_val = t.get(&left, m)?;
+				let mut right_val = t.get(&right, m)?;
+				left_val.swap(&mut right_val)?;
+				
+				// if both refer to the same element, this writes it back
+				// unchanged
+				t.set(&left, m, left_val)?;
+				t.set(&right, m, right_val)?;
 			}
 			
 			/* Clearly we need more info here. Eventually we'll need to store
 			the "path" of the current module with the procedure, but for now
 			just having the items of the current module is good enough. So find
 			a way to make that available. */
-			Stmt::Do(callee_name, args) => {
-				let mut vals = Vec::new();
-				for arg in args {
-					vals.push(arg.eval(t)?);
+			StmtKind::Do(callee_name, args) =>
+				eval_call(t, m, callee_name, args, Dir::Fore, journal.is_recording())?,
+			StmtKind::Undo(callee_name, args) =>
+				eval_call(t, m, callee_name, args, Dir::Back, journal.is_recording())?,
+			
+			StmtKind::If(test, block, else_block, assert) => {
+				let taken = eval_bool(test, t, m)?;
+				let block = if taken { block } else { else_block };
+				for stmt in block {
+					stmt.eval_recorded(t, m, journal)?;
 				}
-				// TODO error handling for when a procedure does not exist.
-				for item in &m.items {
-					match item {
-						Item::Proc(pr) if pr.name == *callee_name => {
-							pr.call(vals, m)?;
-							break;
-						}
-						_ => {}
+				check_assertion(assert, t, m, "`fi` assertion", taken)?;
+			}
+			
+			StmtKind::From(assert, do_block, loop_block, test) => {
+				check_assertion(assert, t, m, "`from` assertion", true)?;
+				loop {
+					for stmt in do_block {
+						stmt.eval_recorded(t, m, journal)?;
+					}
+					
+					if eval_bool(test, t, m)? {
+						break;
+					}
+					
+					for stmt in loop_block {
+						stmt.eval_recorded(t, m, journal)?;
 					}
+					
+					check_assertion(assert, t, m, "`from` assertion", false)?;
 				}
 			}
-			Stmt::Undo(callee_name, args) => {
-				let mut vals = Vec::new();
-				for arg in args {
-					vals.push(arg.eval(t)?);
+			
+			StmtKind::FromVar(id, typ, init, do_block, loop_block, dest) => {
+				let init = init.eval(t, m)?.coerce(typ)?;
+				if *typ != Type::Infer && init.get_type() != *typ {
+					return Err(EvalError::TypeMismatch {
+						expected: typ.clone(),
+						got: init.get_type(),
+					});
 				}
-				for item in &m.items {
-					match item {
-						Item::Proc(pr)
-						if pr.name == *callee_name => {
-							pr.uncall(vals, m)?;
-							break;
-						}
-						_ => {}
+				t.push(id.clone(), init.clone());
+				journal.declare(id);
+				
+				loop {
+					for stmt in do_block {
+						stmt.eval_recorded(t, m, journal)?;
+					}
+					
+					// the loop ends once the counter reaches its final value
+					let dest = dest.eval(t, m)?.coerce(&init.get_type())?;
+					if *counter(t, id)? == dest {
+						break;
+					}
+					
+					for stmt in loop_block {
+						stmt.eval_recorded(t, m, journal)?;
+					}
+					
+					// only the first step may start with the initial value
+					if *counter(t, id)? == init {
+						return Err(EvalError::AssertionFailed {
+							what: "`from var` assertion",
+							expected: false,
+						});
 					}
 				}
+				
+				pop_var(t, id)?;
 			}
 			
-			Stmt::If(test, block, else_block, assert) => {
-				match test.eval(t)? {
-					Value::Bool(true) => {
-						for stmt in block {
-							if let Err(e) = stmt.eval(t, m) {
-								eprintln!("{:?}", stmt);
-								panic!("{:?}", e);
-							}
-						}
-						assert_eq!(assert.eval(t)?, Value::Bool(true));
-					}
-					Value::Bool(false) => {
-						for stmt in else_block {
-							if let Err(e) = stmt.eval(t, m) {
-								eprintln!("{:?}", stmt);
-								panic!("{:?}", e);
-							}
-						}
-						assert_eq!(assert.eval(t)?, Value::Bool(false));
-					}
-					_ => panic!("tried to do something illegal")
+			StmtKind::Match(arms) => {
+				let guards = arms.iter().map(|arm| arm.guard.as_ref());
+				let taken = pick_arm(guards, t, m, "`when` guard")?;
+				
+				for stmt in &arms[taken].block {
+					stmt.eval_recorded(t, m, journal)?;
+				}
+				
+				let asserts = arms.iter().map(|arm| arm.assert.as_ref());
+				if pick_arm(asserts, t, m, "`match` exit assertion")? != taken {
+					return Err(EvalError::AssertionFailed {
+						what: "`match` exit assertion",
+						expected: true,
+					});
 				}
 			}
 			
-			Stmt::From(assert, do_block, loop_block, test) => {
-				assert_eq!(assert.eval(t)?, Value::Bool(true));
-				loop {
-					for stmt in do_block {
-						stmt.eval(t, m)?;
-					}
+			StmtKind::For(name, steps, block, dir) => {
+				let bounds = Bounds::eval(steps, t, m)?;
+				let len = bounds.len();
+				let order: Box<dyn Iterator<Item = usize>> = match dir {
+					Dir::Fore => Box::new(0..len),
+					Dir::Back => Box::new((0..len).rev()),
+				};
+				
+				for i in order {
+					let val = bounds.get(i)?;
+					t.push(name.clone(), val.clone());
+					journal.declare(name);
 					
-					match test.eval(t)? {
-						Value::Bool(true) => break,
-						Value::Bool(false) =>
-							for stmt in loop_block {
-								if let Err(e) = stmt.eval(t, m) {
-									eprintln!("{:?}", stmt);
-									panic!("{:?}", e);
-								}
-							}
-						_ => panic!("tried to do something illegal")
+					for stmt in block {
+						stmt.eval_recorded(t, m, journal)?;
 					}
 					
-					assert_eq!(assert.eval(t)?, Value::Bool(false));
+					// the body may not change the loop variable
+					let final_val = pop_var(t, name)?;
+					if final_val != val {
+						t.push(name.clone(), final_val.clone());
+						return Err(EvalError::DropMismatch {
+							name: name.clone(),
+							expected: val,
+							got: final_val,
+						});
+					}
+					journal.drop(name, &val);
+				}
+				
+				// nor the bounds, or the loop couldn't be run backwards
+				if Bounds::eval(steps, t, m)? != bounds {
+					return Err(EvalError::LoopBoundsModified(name.clone()));
 				}
 			}
 		}
@@ -522,3 +968,306 @@ impl Stmt {
 		Ok(())
 	}
 }
+
+/// Evaluates a condition, which must be a `bool`.
+fn eval_bool(expr: &Expr, t: &StackFrame, m: &Module) -> EvalResult<bool> {
+	match expr.eval(t, m)? {
+		Value::Bool(b) => Ok(b),
+		val => Err(EvalError::TypeMismatch {
+			expected: Type::Bool,
+			got: val.get_type(),
+		}),
+	}
+}
+
+/// Checks that an assertion evaluates to `expected`.
+fn check_assertion(expr: &Expr, t: &StackFrame, m: &Module, what: &'static str, expected: bool) -> EvalResult<()> {
+	if eval_bool(expr, t, m)? == expected {
+		Ok(())
+	} else {
+		Err(EvalError::AssertionFailed { what, expected })
+	}
+}
+
+/// Removes the variable `name` at the end of the block that declared it,
+/// returning its final value. Anything declared after it must have been
+/// dropped by then.
+fn pop_var(t: &mut StackFrame, name: &str) -> EvalResult<Value> {
+	let (final_name, final_val) = t.pop()
+		.ok_or_else(|| EvalError::UnknownIdent(name.to_string()))?;
+	if final_name != name {
+		t.push(final_name.clone(), final_val);
+		return Err(EvalError::UndroppedVar(final_name));
+	}
+	Ok(final_val)
+}
+
+/// The current value of the counter of a `from var` loop.
+fn counter<'a>(t: &'a StackFrame, name: &str) -> EvalResult<&'a Value> {
+	t.lookup(name)
+		.ok_or_else(|| EvalError::UnknownIdent(name.to_string()))
+}
+
+/// The evaluated bounds of a `for` loop.
+#[derive(Debug, PartialEq)]
+enum Bounds {
+	/// The integers of the given type in `start..end`.
+	Range(Type, i128, i128),
+	Each(Box<[Value]>),
+}
+
+impl Bounds {
+	fn eval(steps: &Steps, t: &StackFrame, m: &Module) -> EvalResult<Self> {
+		match steps {
+			Steps::Range(start, end) => {
+				let start = start.eval(t, m)?;
+				let end = end.eval(t, m)?;
+				
+				// plain integers take the type of the other bound
+				let typ = match (start.get_type(), end.get_type()) {
+					(Type::Int, typ) | (typ, Type::Int) => typ,
+					(Type::UInt, typ) | (typ, Type::UInt) => typ,
+					(left, right) if left == right => left,
+					(left, right) => return Err(EvalError::TypeMismatch {
+						expected: left,
+						got: right,
+					}),
+				};
+				Ok(Bounds::Range(typ, loop_bound(start)?, loop_bound(end)?))
+			}
+			Steps::Each(expr) => match expr.eval(t, m)? {
+				Value::Array(array) => Ok(Bounds::Each(array)),
+				val => Err(EvalError::TypeMismatch {
+					expected: Type::Array(0),
+					got: val.get_type(),
+				}),
+			}
+		}
+	}
+	
+	fn len(&self) -> usize {
+		match self {
+			Bounds::Range(_, start, end) =>
+				usize::try_from(end.saturating_sub(*start).max(0)).unwrap_or(usize::MAX),
+			Bounds::Each(array) => array.len(),
+		}
+	}
+	
+	/// The value of the loop variable at step `i`.
+	fn get(&self, i: usize) -> EvalResult<Value> {
+		match self {
+			Bounds::Range(typ, start, _) => {
+				let n = start + i as i128;
+				let val = match typ {
+					Type::Int => i64::try_from(n).ok().map(Value::Int),
+					Type::UInt => u64::try_from(n).ok().map(Value::Uint),
+					Type::Fixed(t) => t.checked_bits(n).map(|bits| Value::Fixed(*t, bits)),
+					_ => Some(Value::BigInt(n.into())),
+				};
+				val.ok_or_else(|| EvalError::IntOutOfRange {
+					value: Value::BigInt(n.into()),
+					typ: typ.clone(),
+				})
+			}
+			Bounds::Each(array) => Ok(array[i].clone()),
+		}
+	}
+}
+
+/// The number that a bound of a `for` loop range stands for.
+fn loop_bound(val: Value) -> EvalResult<i128> {
+	match &val {
+		Value::Int(n) => Ok(*n as i128),
+		Value::Uint(n) => Ok(*n as i128),
+		Value::Fixed(t, bits) => Ok(t.to_i128(*bits)),
+		Value::BigInt(n) => i128::try_from(n)
+			.map_err(|_| EvalError::IntOutOfRange { value: val.clone(), typ: Type::Int }),
+		_ => Err(EvalError::TypeMismatch {
+			expected: Type::Int,
+			got: val.get_type(),
+		}),
+	}
+}
+
+/// Finds the only arm of a `match` whose condition holds. A missing condition
+/// holds if no other one does.
+fn pick_arm<'a, I>(conds: I, t: &StackFrame, m: &Module, what: &'static str) -> EvalResult<usize>
+where I: Iterator<Item = Option<&'a Expr>> {
+	let mut otherwise = None;
+	let mut held = Vec::new();
+	for (i, cond) in conds.enumerate() {
+		match cond {
+			Some(cond) => if eval_bool(cond, t, m)? {
+				held.push(i);
+			}
+			None => otherwise = Some(i),
+		}
+	}
+	
+	match (&held[..], otherwise) {
+		(&[i], _) => Ok(i),
+		([], Some(i)) => Ok(i),
+		_ => Err(EvalError::AmbiguousMatch { what, held: held.len() }),
+	}
+}
+
+/// Calls (or uncalls) a procedure with copy-in copy-out semantics.
+///
+/// Arguments are evaluated and copied into the callee. Afterwards, the final
+/// values of the parameters are copied back to any arguments that are
+/// lvalues, `var` arguments are declared, and `drop` arguments are checked to
+/// be cleared. If the call fails, the caller's variables are left untouched.
+fn eval_call(t: &mut StackFrame, m: &Module, callee_name: &str, args: &[Arg], dir: Dir, transactional: bool) -> EvalResult<()> {
+	// variables holding procedures hide procedures of the same name, but
+	// other variables don't
+	let pr = match (t.lookup(callee_name), m.get_proc(callee_name)) {
+		(Some(Value::Proc(name, _)), _) => m.get_proc(name)
+			.ok_or_else(|| EvalError::UnknownProc(name.clone()))?,
+		(_, Some(pr)) => pr,
+		(Some(val), None) => return Err(EvalError::TypeMismatch {
+			expected: Type::Proc(vec![(false, Type::Infer); args.len()]),
+			got: val.get_type(),
+		}),
+		(None, None) => return Err(EvalError::UnknownProc(callee_name.to_string())),
+	};
+	
+	if args.len() != pr.params.len() {
+		return Err(EvalError::ArityMismatch {
+			name: pr.name.clone(),
+			expected: pr.params.len(),
+			got: args.len(),
+		});
+	}
+	
+	// find where `var` parameters get written back to before the callee
+	// runs, since it may change the variables used in their indices
+	let places = args.iter().zip(&pr.params)
+		.map(|(arg, param)| match arg {
+			Arg::Expr(Expr { kind: ExprKind::LVal(lval), .. }) if param.mutable =>
+				t.resolve(lval, m).map(Some),
+			_ => Ok(None),
+		})
+		.collect::<EvalResult<Vec<_>>>()?;
+	
+	// `drop` arguments are handed over to the callee, so keep them in case
+	// they need to be put back
+	let mut dropped = Vec::new();
+	let result = eval_args(t, m, pr, args, &mut dropped)
+		.and_then(|vals| pr.invoke(dir, vals, m, transactional))
+		.and_then(|vals| {
+			// the callee must hand back cleared values for `drop` arguments
+			for (arg, val) in args.iter().zip(&vals) {
+				if let Arg::Drop(name) = arg {
+					let zero = Value::zero(&val.get_type(), m)
+						.ok_or_else(|| EvalError::NoZeroValue(val.get_type()))?;
+					if *val != zero {
+						return Err(EvalError::DropMismatch {
+							name: name.clone(),
+							expected: zero,
+							got: val.clone(),
+						});
+					}
+				}
+			}
+			Ok(vals)
+		});
+	
+	let vals = match result {
+		Ok(vals) => vals,
+		Err(e) => {
+			for (name, val) in dropped {
+				t.push(name, val);
+			}
+			return Err(e);
+		}
+	};
+	
+	for ((arg, place), val) in args.iter().zip(&places).zip(vals) {
+		match (arg, place) {
+			(_, Some(place)) => t.set(place, m, val)?,
+			(Arg::Var(name), None) => t.push(name.clone(), val),
+			// read-only parameters can't have changed, so skip them
+			(Arg::Expr(_), None) | (Arg::Drop(_), None) => {}
+		}
+	}
+	
+	Ok(())
+}
+
+/// Evaluates the arguments of a call to `pr`, removing the variables of
+/// `drop` arguments from `t` and saving them in `dropped`.
+fn eval_args(t: &mut StackFrame, m: &Module, pr: &Procedure, args: &[Arg], dropped: &mut Vec<(String, Value)>) -> EvalResult<Vec<Value>> {
+	let mut vals = Vec::with_capacity(args.len());
+	for (arg, param) in args.iter().zip(&pr.params) {
+		vals.push(match arg {
+			Arg::Expr(expr) => {
+				// a `var` parameter needs somewhere to copy its value back to
+				if param.mutable && !matches!(expr.kind, ExprKind::LVal(_)) {
+					return Err(EvalError::NotAnLValue(param.name.clone()));
+				}
+				match expr.kind {
+					// the value gets copied back, so it has to keep its type
+					ExprKind::LVal(_) if param.mutable => expr.eval(t, m)?,
+					// constants like `5` can be passed for a `u32` parameter,
+					// and closures for a `fn` parameter
+					_ => expr.eval(t, m)?.coerce(&param.typ)?,
+				}
+			}
+			Arg::Var(_) => Value::zero(&param.typ, m)
+				.ok_or_else(|| EvalError::NoZeroValue(param.typ.clone()))?,
+			Arg::Drop(name) => {
+				let val = t.remove(name)?;
+				dropped.push((name.clone(), val.clone()));
+				val
+			}
+		});
+	}
+	Ok(vals)
+}
+
+#[cfg(test)]
+mod tests {
+	use logos::Logos;
+	
+	use super::*;
+	
+	/// Runs `stmt` with `vars` in a module holding `items`, without any of
+	/// the static checks, and returns the variables afterwards.
+	fn run(items: &str, vars: Vec<(&str, Value)>, stmt: &str) -> StackFrame {
+		let items = Parser::new(Token::lexer(items)).parse_file_module().unwrap();
+		let m = Module::new("test".into(), items);
+		let stmt = Parser::new(Token::lexer(stmt)).parse_stmt().unwrap();
+		
+		let mut t = StackFrame::new(vars.into_iter()
+			.map(|(name, val)| (name.to_string(), val))
+			.collect()
+		);
+		stmt.eval(&mut t, &m).unwrap();
+		t
+	}
+	
+	fn ints(ns: &[i64]) -> Value {
+		Value::Array(ns.iter().copied().map(Value::Int).collect())
+	}
+	
+	const BUMP: &str = "\
+proc bump(var i: int, var x: int)
+	i += 1
+	x += 5
+end
+";
+	
+	#[test]
+	fn write_back_to_place_before_call() {
+		let t = run(BUMP, vec![("i", Value::Int(0)), ("a", ints(&[10, 20, 30]))], "do bump: i, a.(i)\n");
+		assert_eq!(t.lookup("i"), Some(&Value::Int(1)));
+		assert_eq!(t.lookup("a"), Some(&ints(&[15, 20, 30])));
+	}
+	
+	#[test]
+	fn write_back_in_reverse() {
+		let t = run(BUMP, vec![("i", Value::Int(1)), ("a", ints(&[10, 20, 30]))], "undo bump: i, a.(i)\n");
+		assert_eq!(t.lookup("i"), Some(&Value::Int(0)));
+		assert_eq!(t.lookup("a"), Some(&ints(&[10, 15, 30])));
+	}
+}
@@ -1,24 +1,68 @@
 use super::*;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub enum Type {
+	#[default]
 	Infer,
 	Never,
 	Unit,
 	Bool,
 	UInt, Int,
+	/// Integer with a fixed number of bits, which wraps around on overflow.
+	Fixed(IntType),
+	/// Integer that grows as needed, and so never overflows.
+	BigInt,
 	Char, String,
 	//Array(Box<Type>, usize),
 	Array(usize),
+	/// Reference to a heap cell holding a value of the given type.
+	Ref(Box<Type>),
 	Fn(Vec<Type>, Box<Type>),
 	Proc(Vec<(bool, Type)>),
 	//Alternate(Vec<Type>),
-	//Composite(Vec<Type>),
+	/// A record type declared with `struct`, by name.
+	Record(String),
+}
+
+/// Sizes of fixed-width integers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntType {
+	U8, U16, U32, U64,
+	I8, I16, I32, I64,
+}
+
+impl IntType {
+	pub fn from_name(name: &str) -> Option<Self> {
+		Some(match name {
+			"u8"  => IntType::U8,
+			"u16" => IntType::U16,
+			"u32" => IntType::U32,
+			"u64" => IntType::U64,
+			"i8"  => IntType::I8,
+			"i16" => IntType::I16,
+			"i32" => IntType::I32,
+			"i64" => IntType::I64,
+			_ => return None,
+		})
+	}
+	
+	pub fn bits(self) -> u32 {
+		match self {
+			IntType::U8  | IntType::I8  => 8,
+			IntType::U16 | IntType::I16 => 16,
+			IntType::U32 | IntType::I32 => 32,
+			IntType::U64 | IntType::I64 => 64,
+		}
+	}
+	
+	pub fn is_signed(self) -> bool {
+		matches!(self, IntType::I8 | IntType::I16 | IntType::I32 | IntType::I64)
+	}
 }
 
 impl Parser<'_> {
 	pub fn parse_type(&mut self) -> ParseResult<Type> {
-		Ok(match self.peek().ok_or("a type")? {
+		Ok(match self.peek().copied().ok_or_else(|| self.error("a type"))? {
 			Token::Ident => {
 				let name = self.expect_ident().unwrap();
 				match name.as_str() {
@@ -28,9 +72,14 @@ impl Parser<'_> {
 					"bool" => Type::Bool,
 					"uint" => Type::UInt,
 					"int"  => Type::Int,
+					"bigint" => Type::BigInt,
 					"char" => Type::Char,
 					"str"  => Type::String,
-					id     => todo!("custom types not yet supported"),
+					"ref"  => Type::Ref(Box::new(self.parse_type()?)),
+					name   => match IntType::from_name(name) {
+						Some(int_type) => Type::Fixed(int_type),
+						None => Type::Record(name.to_string()),
+					}
 				}
 			}
 			
@@ -38,7 +87,7 @@ impl Parser<'_> {
 				self.next();
 				
 				self.expect(Token::LParen)
-					.ok_or("`(` for `fn` type")?;
+					.ok_or_else(|| self.error("`(` for `fn` type"))?;
 				
 				let mut params = Vec::new();
 				loop {
@@ -50,16 +99,16 @@ impl Parser<'_> {
 							match self.peek() {
 								Some(Token::Comma) => { self.next(); }
 								Some(Token::RParen) => {}
-								_ => Err("`,` or `)` in fn param list")?,
+								_ => Err(self.error("`,` or `)` in fn param list"))?,
 							}
 						}
-						None => Err("`,` or `)` in fn param list")?,
+						None => Err(self.error("`,` or `)` in fn param list"))?,
 					}
 				}
 				self.next();
 				
 				self.expect(Token::Colon)
-					.ok_or("`:` to specify `fn` return type")?;
+					.ok_or_else(|| self.error("`:` to specify `fn` return type"))?;
 				
 				let ret = self.parse_type()?;
 				
@@ -70,7 +119,7 @@ impl Parser<'_> {
 				self.next();
 				
 				self.expect(Token::LParen)
-					.ok_or("`(` for `proc` type")?;
+					.ok_or_else(|| self.error("`(` for `proc` type"))?;
 				
 				let mut params = Vec::new();
 				loop {
@@ -85,10 +134,10 @@ impl Parser<'_> {
 							match self.peek() {
 								Some(Token::Comma) => { self.next(); }
 								Some(Token::RParen) => {}
-								_ => Err("`,` or `)` in fn param list")?,
+								_ => Err(self.error("`,` or `)` in fn param list"))?,
 							}
 						}
-						None => Err("`)` or `,` in proc param list")?,
+						None => Err(self.error("`)` or `,` in proc param list"))?,
 					}
 				}
 				self.next();
@@ -96,11 +145,7 @@ impl Parser<'_> {
 				Type::Proc(params)
 			}
 			
-			_ => Err("a valid type")?
+			_ => Err(self.error("a valid type"))?
 		})
 	}
 }
-
-impl Default for Type {
-	fn default() -> Self { Type::Infer }
-}
@@ -0,0 +1,611 @@
+/*!
+Static checks over the AST, run before a program is interpreted.
+
+These catch mistakes that would otherwise only show up at runtime, if at all.
+Currently they check that:
++ procedures never modify a parameter that isn't declared `var`, other than
+  through a reference.
++ no two arguments of a call refer to the same variable, if the callee may
+  modify either of them.
++ the variable being updated doesn't occur on the right-hand side of its own
+  update, e.g. `x += x`. Updates like `a.(i) += a.(j)`, which are only
+  irreversible if `i = j` at runtime, get a warning instead.
++ the body of a `for` loop doesn't modify the loop variable, or any variable
+  that the bounds of the loop depend on.
++ every type name used in a declaration is either built in or a declared
+  record type.
+*/
+
+use crate::ast::{Arg, Deref, Expr, ExprKind, Item, Literal, LValue, ProcDef, Procedure, Steps, Stmt, StmtKind, Type};
+use crate::diagnostic::Diagnostic;
+use crate::span::Span;
+
+/// Runs all checks over a list of items, returning any problems found.
+pub fn check_items(items: &[Item]) -> Vec<Diagnostic> {
+	let mut checker = Checker { items, diagnostics: Vec::new() };
+	
+	for item in items {
+		match item {
+			Item::Proc(pr) => checker.check_proc(pr),
+			Item::Mod(m) => {
+				let diagnostics = check_items(&m.items);
+				checker.diagnostics.extend(diagnostics);
+			}
+			Item::Fn(f) => {
+				for (_, typ) in &f.params {
+					checker.check_type(typ, f.span);
+				}
+				checker.check_type(&f.ret, f.span);
+			}
+			Item::Record(r) =>
+				for (_, typ) in &r.fields {
+					checker.check_type(typ, r.span);
+				}
+		}
+	}
+	
+	checker.diagnostics
+}
+
+struct Checker<'a> {
+	/// Items visible from the procedure being checked.
+	items: &'a [Item],
+	diagnostics: Vec<Diagnostic>,
+}
+
+/// Names visible inside the procedure being checked.
+struct Scope<'a> {
+	proc: &'a Procedure,
+	/// Local variables, which may shadow parameters, along with as much of
+	/// their types as can be told before running.
+	locals: Vec<(String, Type)>,
+	/// Variables that the body of an enclosing `for` loop can't modify, along
+	/// with the number of locals declared before the loop. Locals declared
+	/// later can shadow them.
+	frozen: Vec<(String, usize)>,
+}
+
+impl Scope<'_> {
+	/// Whether `name` refers to a parameter that isn't declared `var`.
+	fn is_read_only(&self, name: &str) -> bool {
+		!self.locals.iter().any(|(local, _)| local == name)
+		&& self.proc.params.iter()
+			.any(|param| param.name == name && !param.mutable)
+	}
+	
+	/// Whether `name` refers to a variable an enclosing `for` loop depends on.
+	fn is_frozen(&self, name: &str) -> bool {
+		self.frozen.iter()
+			.any(|(frozen, depth)| frozen == name
+				&& !self.locals[*depth..].iter().any(|(local, _)| local == name))
+	}
+	
+	/// Type of the variable `name`, if it's a local or a parameter.
+	fn var_type(&self, name: &str) -> Option<&Type> {
+		match self.locals.iter().rev().find(|(local, _)| local == name) {
+			Some((_, typ)) => Some(typ),
+			None => self.proc.params.iter()
+				.find(|param| param.name == name)
+				.map(|param| &param.typ),
+		}
+	}
+}
+
+impl<'a> Checker<'a> {
+	fn get_proc(&self, name: &str) -> Option<&'a Procedure> {
+		self.items.iter()
+			.find_map(|item| match item {
+				Item::Proc(pr) if pr.name == name => Some(pr),
+				_ => None,
+			})
+	}
+	
+	/// Signature of the procedure that `do name` calls, or `None` if it can't
+	/// be known before running, e.g. if `name` is a variable that may hold any
+	/// procedure.
+	fn get_signature(&self, name: &str, scope: &Scope) -> Option<Vec<(bool, Type)>> {
+		// variables only hide procedures if they hold one
+		match scope.var_type(name) {
+			Some(Type::Proc(signature)) => Some(signature.clone()),
+			Some(Type::Infer) => None,
+			_ => self.get_proc(name).map(Procedure::signature),
+		}
+	}
+	
+	/// As much of the type of `expr` as can be told before running it.
+	fn type_of(&self, expr: &Expr, scope: &Scope) -> Type {
+		match &expr.kind {
+			ExprKind::LVal(lval) if lval.ops.is_empty() => match scope.var_type(&lval.id) {
+				Some(typ) => typ.clone(),
+				None => self.get_proc(&lval.id)
+					.map_or(Type::Infer, |pr| Type::Proc(pr.signature())),
+			}
+			ExprKind::Lit(lit) => lit.get_type().unwrap_or(Type::Infer),
+			_ => expr.get_type().unwrap_or(Type::Infer),
+		}
+	}
+	
+	/// Reports an error if `typ` names a record type that isn't declared.
+	fn check_type(&mut self, typ: &Type, span: Span) {
+		match typ {
+			Type::Record(name) => {
+				let declared = self.items.iter()
+					.any(|item| matches!(item, Item::Record(r) if r.name == *name));
+				if !declared {
+					self.diagnostics.push(Diagnostic::error(
+						format!("unknown type `{}`", name),
+						span,
+					));
+				}
+			}
+			Type::Ref(typ) => self.check_type(typ, span),
+			Type::Fn(params, ret) => {
+				for typ in params {
+					self.check_type(typ, span);
+				}
+				self.check_type(ret, span);
+			}
+			Type::Proc(params) =>
+				for (_, typ) in params {
+					self.check_type(typ, span);
+				}
+			_ => {}
+		}
+	}
+	
+	fn check_proc(&mut self, pr: &Procedure) {
+		for param in &pr.params {
+			self.check_type(&param.typ, param.span);
+		}
+		
+		if let ProcDef::User(code) = &pr.code {
+			let mut scope = Scope { proc: pr, locals: Vec::new(), frozen: Vec::new() };
+			self.check_block(code, &mut scope);
+		}
+	}
+	
+	fn check_block(&mut self, block: &[Stmt], scope: &mut Scope) {
+		// variables declared by `var` arguments live until the end of the block
+		let depth = scope.locals.len();
+		for stmt in block {
+			self.check_stmt(stmt, scope);
+		}
+		scope.locals.truncate(depth);
+	}
+	
+	fn check_stmt(&mut self, stmt: &Stmt, scope: &mut Scope) {
+		match &stmt.kind {
+			StmtKind::Skip => {}
+			
+			StmtKind::RotLeft(lval, expr)
+			| StmtKind::RotRight(lval, expr)
+			| StmtKind::Xor(lval, expr)
+			| StmtKind::Add(lval, expr)
+			| StmtKind::Sub(lval, expr) => {
+				self.check_modified(lval, scope);
+				self.check_update(lval, expr);
+			}
+			
+			StmtKind::Swap(left, right) => {
+				self.check_modified(left, scope);
+				self.check_modified(right, scope);
+			}
+			
+			StmtKind::Do(callee, args)
+			| StmtKind::Undo(callee, args) => {
+				let signature = self.get_signature(callee, scope);
+				
+				// only `var` parameters of the callee can modify the argument,
+				// but without a signature, any of them might
+				let mutable: Vec<bool> = (0..args.len())
+					.map(|i| match &signature {
+						Some(signature) => signature.get(i)
+							.is_some_and(|&(mutable, _)| mutable),
+						None => true,
+					})
+					.collect();
+				
+				self.check_call_aliases(args, &mutable);
+				
+				for (i, (arg, &mutable)) in args.iter().zip(&mutable).enumerate() {
+					match arg {
+						// only report arguments known to be modified
+						Arg::Expr(expr) => match &expr.kind {
+							ExprKind::LVal(lval) if mutable && signature.is_some() =>
+								self.check_modified(lval, scope),
+							_ => {}
+						}
+						Arg::Var(name) => {
+							let typ = signature.as_ref()
+								.and_then(|signature| signature.get(i))
+								.map_or(Type::Infer, |(_, typ)| typ.clone());
+							scope.locals.push((name.clone(), typ));
+						}
+						Arg::Drop(name) if scope.is_read_only(name) =>
+							self.diagnostics.push(Diagnostic::error(
+								format!("cannot drop read-only parameter `{}`", name),
+								stmt.span,
+							)),
+						Arg::Drop(name) if scope.is_frozen(name) =>
+							self.diagnostics.push(Diagnostic::error(
+								format!("cannot drop `{}` in the body of a `for` loop that depends on it", name),
+								stmt.span,
+							)),
+						Arg::Drop(_) => {}
+					}
+				}
+			}
+			
+			StmtKind::Var(name, typ, init, block, _) => {
+				self.check_type(typ, stmt.span);
+				let typ = match typ {
+					Type::Infer => self.type_of(init, scope),
+					typ => typ.clone(),
+				};
+				scope.locals.push((name.clone(), typ));
+				self.check_block(block, scope);
+				scope.locals.pop();
+			}
+			
+			StmtKind::FromVar(name, typ, init, block, back_block, _) => {
+				self.check_type(typ, stmt.span);
+				let typ = match typ {
+					Type::Infer => self.type_of(init, scope),
+					typ => typ.clone(),
+				};
+				scope.locals.push((name.clone(), typ));
+				self.check_block(block, scope);
+				self.check_block(back_block, scope);
+				scope.locals.pop();
+			}
+			
+			StmtKind::If(_, block, else_block, _)
+			| StmtKind::From(_, block, else_block, _) => {
+				self.check_block(block, scope);
+				self.check_block(else_block, scope);
+			}
+			
+			StmtKind::Match(arms) =>
+				for arm in arms {
+					self.check_block(&arm.block, scope);
+				}
+			
+			StmtKind::For(name, steps, block, _) => {
+				let bounds: Vec<&Expr> = match steps {
+					Steps::Range(start, end) => vec![start, end],
+					Steps::Each(array) => vec![array],
+				};
+				
+				// the bounds are evaluated outside the loop variable's scope
+				let depth = scope.locals.len();
+				let frozen = scope.frozen.len();
+				scope.frozen.extend(bounds.into_iter()
+					.flat_map(lvalues_in)
+					.map(|lval| (lval.id.clone(), depth))
+				);
+				
+				// stepping through a range gives integers, not procedures
+				let typ = match steps {
+					Steps::Range(..) => Type::Int,
+					Steps::Each(_) => Type::Infer,
+				};
+				scope.locals.push((name.clone(), typ));
+				scope.frozen.push((name.clone(), depth + 1));
+				self.check_block(block, scope);
+				scope.locals.pop();
+				scope.frozen.truncate(frozen);
+			}
+		}
+	}
+	
+	/// Reports an error if the variable being updated also occurs on the
+	/// right-hand side, since the update then can't be undone. If they only
+	/// might be the same, e.g. `a.(i) += a.(j)`, a warning is given instead.
+	fn check_update(&mut self, target: &LValue, expr: &Expr) {
+		// the target's own indices are read before it's updated, so the
+		// target can't be allowed to move, e.g. with `a.(a.(0)) += 1`
+		let indices = target.ops.iter()
+			.filter_map(|deref| match deref {
+				Deref::Index(index) => Some(index),
+				_ => None,
+			})
+			.flat_map(lvalues_in);
+		
+		for lval in lvalues_in(expr).into_iter().chain(indices) {
+			match overlap(target, lval) {
+				Overlap::No => {}
+				Overlap::Maybe =>
+					self.diagnostics.push(Diagnostic::warning(
+						format!(
+							"this may refer to the same part of `{}` that's being updated, which would make the update irreversible",
+							target.id,
+						),
+						lval.span,
+					)),
+				Overlap::Yes =>
+					self.diagnostics.push(Diagnostic::error(
+						format!(
+							"`{}` must not occur on the right-hand side of its own update",
+							target.id,
+						),
+						lval.span,
+					)),
+			}
+		}
+	}
+	
+	/// Reports an error for every pair of arguments that refer to the same
+	/// variable where the callee may modify at least one of them.
+	fn check_call_aliases(&mut self, args: &[Arg], mutable: &[bool]) {
+		let lvals: Vec<Option<&LValue>> = args.iter()
+			.map(|arg| match arg {
+				Arg::Expr(Expr { kind: ExprKind::LVal(lval), .. }) => Some(lval),
+				_ => None,
+			})
+			.collect();
+		
+		for (i, left) in lvals.iter().enumerate() {
+			let left = match left {
+				Some(left) => left,
+				None => continue,
+			};
+			
+			for (j, right) in lvals.iter().enumerate().skip(i + 1) {
+				match right {
+					Some(right)
+					if (mutable[i] || mutable[j])
+					&& overlap(left, right) == Overlap::Yes =>
+						self.diagnostics.push(Diagnostic::error(
+							format!(
+								"`{}` is passed more than once to the same call",
+								right.id,
+							),
+							right.span,
+						)),
+					_ => {}
+				}
+			}
+			
+			// an argument that is written back must not be used to locate
+			// another argument, since write-back would then move it
+			if mutable[i] {
+				for (j, other) in lvals.iter().enumerate() {
+					let indices = match other {
+						Some(other) if i != j => other.ops.iter(),
+						_ => continue,
+					};
+					for deref in indices {
+						if let Deref::Index(index) = deref {
+							for lval in lvalues_in(index) {
+								if overlap(left, lval) == Overlap::Yes {
+									self.diagnostics.push(Diagnostic::error(
+										format!(
+											"`{}` is modified by the call, so it can't be used as an index in another argument",
+											lval.id,
+										),
+										lval.span,
+									));
+								}
+							}
+						}
+					}
+				}
+			}
+		}
+	}
+	
+	/// Reports an error if `lval` is part of a read-only parameter, or of a
+	/// variable that an enclosing `for` loop depends on. Modifying a heap cell
+	/// through a reference, as in `r! += 1`, leaves the reference itself
+	/// unchanged, so that's allowed.
+	fn check_modified(&mut self, lval: &LValue, scope: &Scope) {
+		let through_ref = lval.ops.iter()
+			.any(|deref| matches!(deref, Deref::Direct));
+		
+		if scope.is_read_only(&lval.id) && !through_ref {
+			self.diagnostics.push(Diagnostic::error(
+				format!(
+					"cannot modify read-only parameter `{}` of proc {}; declare it with `var`",
+					lval.id, scope.proc.name,
+				),
+				lval.span,
+			));
+		}
+		
+		if scope.is_frozen(&lval.id) && !through_ref {
+			self.diagnostics.push(Diagnostic::error(
+				format!(
+					"cannot modify `{}` in the body of a `for` loop that depends on it",
+					lval.id,
+				),
+				lval.span,
+			));
+		}
+	}
+}
+
+/// Whether two lvalues refer to overlapping storage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Overlap {
+	No,
+	/// Can't be known until runtime, e.g. `a.(i)` and `a.(j)`.
+	Maybe,
+	Yes,
+}
+
+/// Compares two lvalues by their access paths. One lvalue that's a prefix of
+/// the other, like `a` and `a.(1)`, overlaps with it.
+fn overlap(left: &LValue, right: &LValue) -> Overlap {
+	if left.id != right.id {
+		return Overlap::No;
+	}
+	
+	let mut result = Overlap::Yes;
+	for pair in left.ops.iter().zip(&right.ops) {
+		match pair {
+			(Deref::Direct, Deref::Direct) => {}
+			(Deref::Field(l), Deref::Field(r)) =>
+				if l != r {
+					return Overlap::No;
+				}
+			(Deref::Index(l), Deref::Index(r)) =>
+				match (const_index(l), const_index(r)) {
+					(Some(l), Some(r)) if l != r => return Overlap::No,
+					(Some(_), Some(_)) => {}
+					_ if same_var(l, r) => {}
+					_ => result = Overlap::Maybe,
+				}
+			_ => result = Overlap::Maybe,
+		}
+	}
+	
+	result
+}
+
+/// Value of an index expression, if it's a constant.
+fn const_index(expr: &Expr) -> Option<i64> {
+	match &expr.kind {
+		ExprKind::Lit(Literal::Int(i)) => Some(*i),
+		ExprKind::Lit(Literal::UInt(u)) => Some(*u as i64),
+		_ => None,
+	}
+}
+
+/// Whether both expressions are the same plain variable, like `i` and `i`.
+fn same_var(left: &Expr, right: &Expr) -> bool {
+	match (&left.kind, &right.kind) {
+		(ExprKind::LVal(l), ExprKind::LVal(r)) =>
+			l.id == r.id && l.ops.is_empty() && r.ops.is_empty(),
+		_ => false,
+	}
+}
+
+/// Collects every lvalue read by an expression, including those used as
+/// indices.
+fn lvalues_in(expr: &Expr) -> Vec<&LValue> {
+	fn visit<'a>(expr: &'a Expr, out: &mut Vec<&'a LValue>) {
+		match &expr.kind {
+			ExprKind::Lit(Literal::Array(elems)) =>
+				for elem in elems {
+					visit(elem, out);
+				}
+			ExprKind::Lit(Literal::Record(_, fields)) =>
+				for (_, value) in fields {
+					visit(value, out);
+				}
+			ExprKind::Lit(_) => {}
+			ExprKind::LVal(lval) => {
+				out.push(lval);
+				for deref in &lval.ops {
+					if let Deref::Index(index) = deref {
+						visit(index, out);
+					}
+				}
+			}
+			ExprKind::Call(_, args) =>
+				for arg in args {
+					visit(arg, out);
+				}
+			ExprKind::Cast(expr, _)
+			| ExprKind::Neg(expr)
+			| ExprKind::Not(expr) =>
+				visit(expr, out),
+			ExprKind::BinOp(left, _, right) => {
+				visit(left, out);
+				visit(right, out);
+			}
+		}
+	}
+	
+	let mut lvals = Vec::new();
+	visit(expr, &mut lvals);
+	lvals
+}
+
+#[cfg(test)]
+mod tests {
+	use logos::Logos;
+	
+	use super::*;
+	use crate::ast::Parser;
+	use crate::diagnostic::Level;
+	use crate::token::Token;
+	
+	fn expr(src: &str) -> Expr {
+		Parser::new(Token::lexer(src)).parse_expr()
+			.unwrap_or_else(|e| panic!("failed to parse `{}`: {}", src, e))
+	}
+	
+	fn lval(src: &str) -> LValue {
+		match expr(src).kind {
+			ExprKind::LVal(lval) => lval,
+			_ => panic!("`{}` isn't an lvalue", src),
+		}
+	}
+	
+	fn levels(checker: &Checker) -> Vec<Level> {
+		checker.diagnostics.iter()
+			.map(|diagnostic| diagnostic.level)
+			.collect()
+	}
+	
+	fn update(target: &str, value: &str) -> Vec<Level> {
+		let mut checker = Checker { items: &[], diagnostics: Vec::new() };
+		checker.check_update(&lval(target), &expr(value));
+		levels(&checker)
+	}
+	
+	fn call(args: &[&str], mutable: &[bool]) -> Vec<Level> {
+		let args: Vec<Arg> = args.iter()
+			.map(|arg| Arg::Expr(expr(arg)))
+			.collect();
+		let mut checker = Checker { items: &[], diagnostics: Vec::new() };
+		checker.check_call_aliases(&args, mutable);
+		levels(&checker)
+	}
+	
+	#[test]
+	fn overlap_by_path() {
+		assert_eq!(overlap(&lval("x"), &lval("y")), Overlap::No);
+		assert_eq!(overlap(&lval("x"), &lval("x")), Overlap::Yes);
+		assert_eq!(overlap(&lval("a"), &lval("a.(1)")), Overlap::Yes);
+		assert_eq!(overlap(&lval("a.(1)"), &lval("a.(1)")), Overlap::Yes);
+		assert_eq!(overlap(&lval("a.(1)"), &lval("a.(2)")), Overlap::No);
+		assert_eq!(overlap(&lval("a.(i)"), &lval("a.(i)")), Overlap::Yes);
+		assert_eq!(overlap(&lval("a.(i)"), &lval("a.(j)")), Overlap::Maybe);
+		assert_eq!(overlap(&lval("r.x"), &lval("r.y")), Overlap::No);
+	}
+	
+	#[test]
+	fn update_of_itself() {
+		assert_eq!(update("x", "x"), [Level::Error]);
+		assert_eq!(update("x", "y + 1"), []);
+		assert_eq!(update("a.(0)", "a.(1)"), []);
+	}
+	
+	#[test]
+	fn update_of_maybe_same_index() {
+		assert_eq!(update("a.(i)", "a.(j)"), [Level::Warning]);
+		assert_eq!(update("a.(i)", "a.(i)"), [Level::Error]);
+	}
+	
+	#[test]
+	fn update_moving_own_index() {
+		assert_eq!(update("a.(a.(0))", "1"), [Level::Warning]);
+		assert_eq!(update("a.(i)", "1"), []);
+	}
+	
+	#[test]
+	fn call_with_same_arg_twice() {
+		assert_eq!(call(&["x", "x"], &[true, false]), [Level::Error]);
+		assert_eq!(call(&["x", "x"], &[false, false]), []);
+		assert_eq!(call(&["x", "y"], &[true, true]), []);
+		assert_eq!(call(&["a.(0)", "a.(1)"], &[true, true]), []);
+	}
+	
+	#[test]
+	fn call_moving_index_by_write_back() {
+		assert_eq!(call(&["i", "a.(i)"], &[true, false]), [Level::Error]);
+		assert_eq!(call(&["i", "a.(i)"], &[false, true]), []);
+	}
+}
@@ -0,0 +1,80 @@
+/*! Rendering of parse and runtime errors against the original source. */
+
+use std::fmt;
+
+use crate::span::Span;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+	Error,
+	Warning,
+	Note,
+}
+
+/// A message pointing at a region of a source file.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+	pub level: Level,
+	pub message: String,
+	pub span: Span,
+}
+
+impl Diagnostic {
+	pub fn error(message: impl Into<String>, span: Span) -> Self {
+		Diagnostic { level: Level::Error, message: message.into(), span }
+	}
+	
+	pub fn warning(message: impl Into<String>, span: Span) -> Self {
+		Diagnostic { level: Level::Warning, message: message.into(), span }
+	}
+	
+	pub fn note(message: impl Into<String>, span: Span) -> Self {
+		Diagnostic { level: Level::Note, message: message.into(), span }
+	}
+	
+	/// Renders the diagnostic in a rustc-like format, e.g.:
+	///
+	/// ```text
+	/// error: expected newline after statement
+	///  --> examples/fib.rvr:3:9
+	///   |
+	/// 3 |     x +=
+	///   |         ^
+	/// ```
+	pub fn render(&self, path: &str, source: &str) -> String {
+		let (line, col) = self.span.line_col(source);
+		
+		let start = self.span.start.min(source.len());
+		let line_start = source[..start].rfind('\n').map_or(0, |i| i + 1);
+		let line_end = source[start..].find('\n').map_or(source.len(), |i| start + i);
+		let text = &source[line_start..line_end];
+		
+		// keep tabs so the caret lines up with the source line
+		let padding: String = source[line_start..start].chars()
+			.map(|c| if c == '\t' { '\t' } else { ' ' })
+			.collect();
+		let end = self.span.end.min(line_end).max(start);
+		let width = source[start..end].chars().count().max(1);
+		
+		let gutter = " ".repeat(line.to_string().len());
+		
+		format!(
+			"{}: {}\n{}--> {}:{}:{}\n{} |\n{} | {}\n{} | {}{}",
+			self.level, self.message,
+			gutter, path, line, col,
+			gutter,
+			line, text,
+			gutter, padding, "^".repeat(width),
+		)
+	}
+}
+
+impl fmt::Display for Level {
+	fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Level::Error   => fmt.write_str("error"),
+			Level::Warning => fmt.write_str("warning"),
+			Level::Note    => fmt.write_str("note"),
+		}
+	}
+}
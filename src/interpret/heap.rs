@@ -0,0 +1,120 @@
+/*!
+Heap cells that references (`ref T` values) point to.
+
+Cells are created by the `alloc` intrinsic, which starts them at the zero value
+of their type, and destroyed by `free`, which is its inverse and so requires
+the cell to be back at zero. This way, no information is lost when a cell goes
+away.
+*/
+
+use std::cell::RefCell;
+
+use super::{EvalError, EvalResult, Value};
+
+thread_local! {
+	static HEAP: RefCell<Heap> = const { RefCell::new(Heap { cells: Vec::new(), free: Vec::new() }) };
+}
+
+#[derive(Debug)]
+struct Heap {
+	/// Values of the cells, or `None` for cells that were freed.
+	cells: Vec<Option<Value>>,
+	/// Indices of freed cells, for `alloc` to reuse.
+	free: Vec<usize>,
+}
+
+/// Creates a new cell holding `value`, reusing a freed cell if there is one.
+///
+/// A copy of a reference to a freed cell may therefore end up pointing at an
+/// unrelated cell, rather than staying dangling.
+pub fn alloc(value: Value) -> usize {
+	HEAP.with(|heap| {
+		let heap = &mut *heap.borrow_mut();
+		match heap.free.pop() {
+			Some(cell) => {
+				heap.cells[cell] = Some(value);
+				cell
+			}
+			None => {
+				heap.cells.push(Some(value));
+				heap.cells.len() - 1
+			}
+		}
+	})
+}
+
+/// Destroys a cell, returning the value it held.
+pub fn free(cell: usize) -> EvalResult<Value> {
+	HEAP.with(|heap| {
+		let heap = &mut *heap.borrow_mut();
+		let value = heap.cells.get_mut(cell)
+			.and_then(Option::take)
+			.ok_or(EvalError::DanglingRef)?;
+		heap.free.push(cell);
+		Ok(value)
+	})
+}
+
+/// Returns a copy of the value in a cell.
+pub fn get(cell: usize) -> EvalResult<Value> {
+	HEAP.with(|heap| {
+		heap.borrow().cells.get(cell)
+			.and_then(Option::clone)
+			.ok_or(EvalError::DanglingRef)
+	})
+}
+
+/// Modifies the value in a cell in place with `f`.
+pub fn update<F>(cell: usize, f: F) -> EvalResult<()>
+where F: FnOnce(&mut Value) -> EvalResult<()> {
+	HEAP.with(|heap| {
+		match heap.borrow_mut().cells.get_mut(cell) {
+			Some(Some(value)) => f(value),
+			_ => Err(EvalError::DanglingRef),
+		}
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	fn len() -> usize {
+		HEAP.with(|heap| heap.borrow().cells.len())
+	}
+	
+	#[test]
+	fn freed_cells_are_reused() {
+		let a = alloc(Value::Int(1));
+		let b = alloc(Value::Int(2));
+		assert_eq!(free(a).unwrap(), Value::Int(1));
+		assert!(matches!(get(a), Err(EvalError::DanglingRef)));
+		
+		let c = alloc(Value::Int(3));
+		assert_eq!(c, a);
+		assert_eq!(get(c).unwrap(), Value::Int(3));
+		assert_eq!(get(b).unwrap(), Value::Int(2));
+	}
+	
+	#[test]
+	fn alloc_free_loop_keeps_heap_size() {
+		let before = len();
+		for i in 0..1000 {
+			let cell = alloc(Value::Int(i));
+			free(cell).unwrap();
+		}
+		assert!(len() <= before + 1);
+	}
+	
+	#[test]
+	fn double_free() {
+		let cell = alloc(Value::Int(0));
+		free(cell).unwrap();
+		assert!(matches!(free(cell), Err(EvalError::DanglingRef)));
+		
+		// the cell must only be handed out once
+		let a = alloc(Value::Int(1));
+		let b = alloc(Value::Int(2));
+		assert_ne!(a, b);
+	}
+}
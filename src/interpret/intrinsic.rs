@@ -1,14 +1,16 @@
 use super::{EvalResult, EvalError, Value};
-use crate::ast::Type;
+use crate::ast::{Module, Type};
 use std::io::prelude::*;
 
-pub fn show(args: &mut [Value]) -> EvalResult<()> {
-	assert!(args.len() == 1);
+pub fn show(args: &mut [Value], m: &Module) -> EvalResult<()> {
+	check_arity("show", args, 1)?;
 	
 	let mut rstdout = super::io::RevStdout::new();
 	
 	if let Value::String(string) = &args[0] {
-		rstdout.write(string.as_bytes()).unwrap();
+		// a failed write has nothing to undo, so treat it as irreversible
+		rstdout.write_all(string.as_bytes())
+			.map_err(|_| EvalError::IrreversibleState)?;
 		Ok(())
 	} else {
 		Err(EvalError::TypeMismatch {
@@ -18,15 +20,16 @@ pub fn show(args: &mut [Value]) -> EvalResult<()> {
 	}
 }
 
-pub fn unshow(args: &mut [Value]) -> EvalResult<()> {
-	assert!(args.len() == 1);
+pub fn unshow(args: &mut [Value], m: &Module) -> EvalResult<()> {
+	check_arity("show", args, 1)?;
 	
 	let mut rstdout = super::io::RevStdout::new();
 	
 	if let Value::String(string) = &args[0] {
-		let extracted_data = rstdout.unwrite(string.len());
-		assert_eq!(string.as_bytes(), extracted_data.as_slice());
-		Ok(())
+		match rstdout.unwrite(string.len()) {
+			Some(data) if data == string.as_bytes() => Ok(()),
+			_ => Err(EvalError::IrreversibleState),
+		}
 	} else {
 		Err(EvalError::TypeMismatch {
 			expected: Type::String,
@@ -35,22 +38,73 @@ pub fn unshow(args: &mut [Value]) -> EvalResult<()> {
 	}
 }
 
+// Arguments: r:ref T
+// Action: points the null reference r at a new cell holding the zero value of T.
+pub fn alloc(args: &mut [Value], m: &Module) -> EvalResult<()> {
+	check_arity("alloc", args, 1)?;
+	
+	match &mut args[0] {
+		Value::Ref(typ, cell) => {
+			if cell.is_some() {
+				return Err(EvalError::AlreadyAllocated);
+			}
+			let zero = Value::zero(typ, m)
+				.ok_or_else(|| EvalError::NoZeroValue(typ.clone()))?;
+			*cell = Some(super::heap::alloc(zero));
+			Ok(())
+		}
+		val => Err(EvalError::TypeMismatch {
+			expected: Type::Ref(Box::new(Type::Infer)),
+			got: val.get_type(),
+		}),
+	}
+}
+
+// Arguments: r:ref T
+// Action: frees the cell r points to, which must hold the zero value of T, and
+//         makes r null again.
+pub fn free(args: &mut [Value], m: &Module) -> EvalResult<()> {
+	check_arity("free", args, 1)?;
+	
+	match &mut args[0] {
+		Value::Ref(typ, cell) => {
+			let idx = cell.ok_or(EvalError::NullDeref)?;
+			let value = super::heap::get(idx)?;
+			if Some(&value) != Value::zero(typ, m).as_ref() {
+				return Err(EvalError::FreedNonZero(value));
+			}
+			super::heap::free(idx)?;
+			*cell = None;
+			Ok(())
+		}
+		val => Err(EvalError::TypeMismatch {
+			expected: Type::Ref(Box::new(Type::Infer)),
+			got: val.get_type(),
+		}),
+	}
+}
+
 // Arguments: str:String, bytes:Uint
 // Action: moves str to stdout, increments bytes by number of bytes written.
-pub fn print(args: &mut [Value]) -> EvalResult<()> {
+pub fn print(args: &mut [Value], m: &Module) -> EvalResult<()> {
 	let mut rstdout = super::io::RevStdout::new();
 	
 	match args {
 		[Value::String(string), Value::Uint(bytes)] => {
-			*bytes += rstdout.write(string.as_bytes()).unwrap() as u64;
+			*bytes += rstdout.write(string.as_bytes())
+				.map_err(|_| EvalError::IrreversibleState)? as u64;
 		}
-		[Value::String(_), val] |
+		[Value::String(_), val] =>
+			return Err(EvalError::TypeMismatch {
+				expected: Type::UInt,
+				got: val.get_type(),
+			}),
 		[val, _] =>
 			return Err(EvalError::TypeMismatch {
 				expected: Type::String,
 				got: val.get_type(),
 			}),
-		_ => panic!("wrong number of parameters: expected 2, got not 2"),
+		_ => check_arity("print", args, 2)?,
 	}
 	
 	Ok(())
@@ -59,22 +113,40 @@ pub fn print(args: &mut [Value]) -> EvalResult<()> {
 // Arguments: str:String, bytes:Uint
 // Action: decrements bytes by number of bytes that will be read, and moves
 //         stdout data into str.
-pub fn unprint(args: &mut [Value]) -> EvalResult<()> {
+pub fn unprint(args: &mut [Value], m: &Module) -> EvalResult<()> {
 	let mut rstdout = super::io::RevStdout::new();
 	
 	match args {
 		[Value::String(string), Value::Uint(len)] => {
-			let s = String::from_utf8(rstdout.unwrite(*len as usize)).unwrap();
+			let s = rstdout.unwrite(*len as usize)
+				.and_then(|data| String::from_utf8(data).ok())
+				.ok_or(EvalError::IrreversibleState)?;
 			*len -= s.len() as u64;
 		}
-		[Value::String(_), val] |
+		[Value::String(_), val] =>
+			return Err(EvalError::TypeMismatch {
+				expected: Type::UInt,
+				got: val.get_type(),
+			}),
 		[val, _] =>
 			return Err(EvalError::TypeMismatch {
 				expected: Type::String,
 				got: val.get_type(),
 			}),
-		_ => panic!("wrong number of parameters: expected 2, got not 2"),
+		_ => check_arity("print", args, 2)?,
 	}
 	
 	Ok(())
 }
+
+fn check_arity(proc: &str, args: &[Value], expected: usize) -> EvalResult<()> {
+	if args.len() == expected {
+		Ok(())
+	} else {
+		Err(EvalError::ArityMismatch {
+			name: proc.to_string(),
+			expected,
+			got: args.len(),
+		})
+	}
+}
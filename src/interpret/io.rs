@@ -1,3 +1,4 @@
+use std::cell::RefCell;
 use std::io;
 use std::io::prelude::*;
 
@@ -6,6 +7,13 @@ use std::io::prelude::*;
 //trait RevSeek;
 //struct RevFile;
 
+thread_local! {
+	/// Data written to stdout that hasn't been unwritten yet. This is shared
+	/// by all handles, so that data can be unwritten by a different handle
+	/// than the one that wrote it.
+	static STDOUT_HISTORY: RefCell<Vec<u8>> = const { RefCell::new(Vec::new()) };
+}
+
 /** A handle to a reversible standard output stream.
 
 To allow retrieving data from stdout (e.g. when backtracking or going in
@@ -15,26 +23,29 @@ reverse), a backup buffer is kept of all the data that was passed. When
 #[derive(Debug)]
 pub struct RevStdout {
 	stdout: io::Stdout,
-	history: Vec<u8>,
 }
 
 impl RevStdout {
 	pub fn new() -> Self {
 		RevStdout {
 			stdout: io::stdout(),
-			history: Vec::new(),
 		}
 	}
 	
-	pub fn unwrite(&mut self, bytes_read: usize) -> Vec<u8> {
-		let len = self.history.len() - bytes_read;
-		self.history.split_off(len)
+	/// Takes the last `bytes_read` bytes back out of the history, or returns
+	/// `None` if fewer than that many were written.
+	pub fn unwrite(&mut self, bytes_read: usize) -> Option<Vec<u8>> {
+		STDOUT_HISTORY.with(|history| {
+			let mut history = history.borrow_mut();
+			let len = history.len().checked_sub(bytes_read)?;
+			Some(history.split_off(len))
+		})
 	}
 	
 	/// When this function is called, all data will be lost, and we won't be
 	/// able to go any further in reverse if something goes wrong.
 	pub fn reset(&mut self) {
-		self.history.clear();
+		STDOUT_HISTORY.with(|history| history.borrow_mut().clear());
 	}
 }
 
@@ -42,7 +53,9 @@ impl RevStdout {
 impl Write for RevStdout {
 	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
 		let bytes_read = self.stdout.write(buf)?;
-		self.history.extend_from_slice(&buf[..bytes_read]);
+		STDOUT_HISTORY.with(|history| {
+			history.borrow_mut().extend_from_slice(&buf[..bytes_read]);
+		});
 		Ok(bytes_read)
 	}
 	
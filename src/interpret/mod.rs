@@ -1,10 +1,13 @@
 //use std::io::prelude::*;
+use std::fmt;
 
-use crate::ast::{self, Item, Module, Type, Procedure, Param, ProcDef};
+use crate::ast::{self, BinOp, BlockExpr, Deref, Dir, Expr, ExprKind, Function, Item, LValue, Module, Type, Procedure, Param, ProcDef};
+use crate::span::Span;
 
-pub use self::value::Value;
+pub use self::value::{Closure, Value};
 pub use self::stack::{Stack, StackFrame};
 
+mod heap;
 mod io;
 mod value;
 mod intrinsic;
@@ -14,7 +17,7 @@ mod stack;
 pub type EvalResult<T> = Result<T, EvalError>;
 
 pub trait Eval {
-	fn eval(&self, scope: &StackFrame) -> EvalResult<Value>;
+	fn eval(&self, scope: &StackFrame, m: &Module) -> EvalResult<Value>;
 }
 
 #[derive(Debug)]
@@ -24,28 +27,295 @@ pub enum EvalError {
 		got: Type,
 	},
 	UnknownIdent(String),
+	UnknownProc(String),
+	UnknownFn(String),
+	UnknownField {
+		typ: Type,
+		field: String,
+	},
+	/// A record literal didn't give a value for one of the record's fields.
+	MissingField {
+		typ: Type,
+		field: String,
+	},
+	/// No record type has the name used in a record literal.
+	UnknownRecord(String),
+	/// A built-in field like `len` was the target of a modification.
+	ReadOnlyField(String),
+	/// A null reference was dereferenced.
+	NullDeref,
+	/// A reference to a cell that was already freed was dereferenced.
+	DanglingRef,
+	/// `alloc` was given a reference that already points to a cell.
+	AlreadyAllocated,
+	/// `free` was given a cell that doesn't hold the zero value of its type.
+	FreedNonZero(Value),
+	/// An integer doesn't fit in the type it's converted to.
+	IntOutOfRange {
+		value: Value,
+		typ: Type,
+	},
+	/// A character was updated to a code point that isn't a valid `char`.
+	InvalidCharCode(i64),
 	IrreversibleState,
+	/// An `if`, `fi`, `from`, or `until` expression had the wrong truth value.
+	AssertionFailed {
+		/// Which part of the statement failed, e.g. "`fi` assertion".
+		what: &'static str,
+		expected: bool,
+	},
+	/// Not exactly one `when` guard or exit assertion of a `match` held.
+	AmbiguousMatch {
+		/// Which kind of condition, e.g. "`when` guard".
+		what: &'static str,
+		held: usize,
+	},
+	/// A variable didn't have the expected value when it was dropped.
+	DropMismatch {
+		name: String,
+		expected: Value,
+		got: Value,
+	},
+	/// A variable was still live when the block declaring it ended.
+	UndroppedVar(String),
+	/// The body of the `for` loop over the given variable changed what the
+	/// loop steps through.
+	LoopBoundsModified(String),
+	IndexOutOfBounds {
+		index: i64,
+		len: usize,
+	},
+	ArityMismatch {
+		/// Name of the procedure or function.
+		name: String,
+		expected: usize,
+		got: usize,
+	},
+	DivByZero,
+	/// An `int` or `uint` operation overflowed while in checked mode.
+	Overflow,
+	/// An `int` was raised to a negative power.
+	NegativeExponent(i64),
+	/// An integer was shifted by a negative or absurdly large amount.
+	InvalidShift(i128),
+	InvalidCast {
+		from: Type,
+		to: Type,
+	},
+	InvalidOperands {
+		op: BinOp,
+		left: Type,
+		right: Type,
+	},
+	/// A language feature that the interpreter doesn't handle yet.
+	Unsupported(&'static str),
+	/// A `var` argument was given for a parameter whose type has no zero
+	/// value to start from, or a `drop` argument has no zero value to be
+	/// cleared to.
+	NoZeroValue(Type),
+	/// A `var` parameter was given an argument that can't be assigned to.
+	NotAnLValue(String),
+	/// A procedure modified a parameter that isn't declared `var`.
+	ReadOnlyModified(String),
+	/// A transactional call failed, and undoing what it had done so far
+	/// failed too.
+	RollbackFailed {
+		error: Box<EvalError>,
+		cause: Box<EvalError>,
+	},
+	/// An error along with the Rever call stack at the point it was raised,
+	/// innermost frame first.
+	Traced(Box<EvalError>, Vec<Frame>),
+}
+
+/// A procedure call that was in progress when an error was raised.
+#[derive(Debug, Clone)]
+pub struct Frame {
+	/// The procedure being run, and in which direction. This is only `None`
+	/// until the error propagates out of the procedure.
+	pub proc: Option<(String, Dir)>,
+	/// The statement that was running in the procedure, if it has one.
+	pub span: Option<Span>,
+}
+
+impl EvalError {
+	/// Records that the error was raised by, or propagated through, the
+	/// statement at `span`.
+	pub fn at(self, span: Span) -> Self {
+		match self {
+			EvalError::Traced(e, mut stack) => {
+				match stack.last() {
+					// an inner statement of the same procedure already
+					// recorded a more precise location
+					Some(Frame { proc: None, .. }) => {}
+					// this is the call statement in the caller
+					_ => stack.push(Frame { proc: None, span: Some(span) }),
+				}
+				EvalError::Traced(e, stack)
+			}
+			e => EvalError::Traced(Box::new(e), vec![
+				Frame { proc: None, span: Some(span) }
+			]),
+		}
+	}
+	
+	/// Records that the error propagated out of a call to the procedure
+	/// `name`.
+	pub fn in_proc(self, name: &str, dir: Dir) -> Self {
+		let proc = Some((name.to_string(), dir));
+		match self {
+			EvalError::Traced(e, mut stack) => {
+				match stack.last_mut() {
+					Some(frame @ Frame { proc: None, .. }) =>
+						frame.proc = proc,
+					_ => stack.push(Frame { proc, span: None }),
+				}
+				EvalError::Traced(e, stack)
+			}
+			e => EvalError::Traced(Box::new(e), vec![
+				Frame { proc, span: None }
+			]),
+		}
+	}
+	
+	/// Records that rolling back a failed transactional call also failed with
+	/// `cause`, keeping the backtrace of the original error.
+	pub fn rollback_failed(self, cause: EvalError) -> Self {
+		let (error, stack) = match self {
+			EvalError::Traced(e, stack) => (e, stack),
+			e => (Box::new(e), Vec::new()),
+		};
+		let cause = Box::new(cause);
+		EvalError::Traced(Box::new(EvalError::RollbackFailed { error, cause }), stack)
+	}
+	
+	/// The error without any backtrace.
+	pub fn inner(&self) -> &EvalError {
+		match self {
+			EvalError::Traced(e, _) => e,
+			e => e,
+		}
+	}
+	
+	/// The call stack when the error was raised, innermost frame first.
+	pub fn backtrace(&self) -> &[Frame] {
+		match self {
+			EvalError::Traced(_, stack) => stack,
+			_ => &[],
+		}
+	}
+	
+	/// Location of the innermost statement that raised the error, if any.
+	pub fn span(&self) -> Option<Span> {
+		self.backtrace().iter()
+			.find_map(|frame| frame.span)
+	}
 }
 
+impl fmt::Display for EvalError {
+	fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			EvalError::TypeMismatch { expected, got } =>
+				write!(fmt, "type mismatch: expected {:?}, got {:?}", expected, got),
+			EvalError::UnknownIdent(id) =>
+				write!(fmt, "unknown identifier `{}`", id),
+			EvalError::UnknownProc(name) =>
+				write!(fmt, "no procedure named `{}`", name),
+			EvalError::UnknownFn(name) =>
+				write!(fmt, "no function named `{}`", name),
+			EvalError::UnknownField { typ, field } =>
+				write!(fmt, "type {:?} has no field `{}`", typ, field),
+			EvalError::MissingField { typ, field } =>
+				write!(fmt, "missing field `{}` for type {:?}", field, typ),
+			EvalError::UnknownRecord(name) =>
+				write!(fmt, "no record type named `{}`", name),
+			EvalError::ReadOnlyField(field) =>
+				write!(fmt, "field `{}` can't be modified", field),
+			EvalError::NullDeref =>
+				fmt.write_str("dereferenced a null reference"),
+			EvalError::DanglingRef =>
+				fmt.write_str("dereferenced a reference to a freed cell"),
+			EvalError::AlreadyAllocated =>
+				fmt.write_str("can't allocate a cell for a reference that already has one"),
+			EvalError::FreedNonZero(value) =>
+				write!(fmt, "can't free a cell holding {}; it must be cleared first", value),
+			EvalError::IntOutOfRange { value, typ } =>
+				write!(fmt, "{} doesn't fit in type {:?}", value, typ),
+			EvalError::InvalidCharCode(code) =>
+				write!(fmt, "{} is not a valid character code", code),
+			EvalError::IrreversibleState =>
+				fmt.write_str("reached an irreversible state"),
+			EvalError::AssertionFailed { what, expected } =>
+				write!(fmt, "{} was {}, but should have been {}", what, !expected, expected),
+			EvalError::AmbiguousMatch { what, held } =>
+				write!(fmt, "{} {}s held, but exactly one should have", held, what),
+			EvalError::DropMismatch { name, expected, got } =>
+				write!(fmt, "variable `{}` was dropped with value {}, expected {}", name, got, expected),
+			EvalError::UndroppedVar(name) =>
+				write!(fmt, "variable `{}` was never dropped", name),
+			EvalError::LoopBoundsModified(name) =>
+				write!(fmt, "the bounds of the `for` loop over `{}` were modified by its body", name),
+			EvalError::IndexOutOfBounds { index, len } =>
+				write!(fmt, "index {} is out of bounds for length {}", index, len),
+			EvalError::ArityMismatch { name, expected, got } =>
+				write!(fmt, "`{}` takes {} argument(s), but {} were given", name, expected, got),
+			EvalError::DivByZero =>
+				fmt.write_str("division by zero"),
+			EvalError::Overflow =>
+				fmt.write_str("integer overflow"),
+			EvalError::NegativeExponent(exp) =>
+				write!(fmt, "can't raise an integer to the negative power {}", exp),
+			EvalError::InvalidShift(by) =>
+				write!(fmt, "can't shift an integer by {} bits", by),
+			EvalError::InvalidCast { from, to } =>
+				write!(fmt, "can't cast {:?} to {:?}", from, to),
+			EvalError::InvalidOperands { op, left, right } =>
+				write!(fmt, "can't apply {:?} to {:?} and {:?}", op, left, right),
+			EvalError::Unsupported(what) =>
+				write!(fmt, "{} not supported yet", what),
+			EvalError::NoZeroValue(typ) =>
+				write!(fmt, "type {:?} has no zero value for a `var` or `drop` argument", typ),
+			EvalError::NotAnLValue(param) =>
+				write!(fmt, "argument for `var` parameter `{}` must be a variable", param),
+			EvalError::ReadOnlyModified(param) =>
+				write!(fmt, "read-only parameter `{}` was modified", param),
+			EvalError::RollbackFailed { error, cause } =>
+				write!(fmt, "{}; undoing the failed call also failed: {}", error, cause),
+			EvalError::Traced(e, _) => e.fmt(fmt),
+		}
+	}
+}
 
-pub fn interpret_file(items: Vec<ast::Item>) {
+impl fmt::Display for Dir {
+	fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Dir::Fore => fmt.write_str("do"),
+			Dir::Back => fmt.write_str("undo"),
+		}
+	}
+}
+
+
+/// Settings for running a program.
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+	/// Undo everything a procedure did if it fails part-way through, so that
+	/// callers see either all of its effects or none of them.
+	pub transactional: bool,
+	/// Report an error when `int` or `uint` arithmetic overflows, instead of
+	/// wrapping around.
+	pub checked: bool,
+}
+
+pub fn interpret_file(items: Vec<ast::Item>, options: &Options) -> EvalResult<()> {
 	// create root module
 	let mut root = Module::new("root".into(), items);
-		
-	root.items.push(
-		Item::Proc(Procedure {
-			name: "show".to_string(),
-			params: vec![Param {
-				name: "string".to_string(),
-				mutable: false,
-				typ: Type::String,
-			}],
-			code: ProcDef::Internal {
-				fore: intrinsic::show,
-				back: intrinsic::unshow
-			},
-		})
-	);
+	root.checked = options.checked;
+	
+	root.items.push(intrinsic_proc("show", ("string", false, Type::String), intrinsic::show, intrinsic::unshow));
+	root.items.push(intrinsic_proc("alloc", ("r", true, Type::Infer), intrinsic::alloc, intrinsic::free));
+	root.items.push(intrinsic_proc("free", ("r", true, Type::Infer), intrinsic::free, intrinsic::alloc));
+	root.items.push(size_fn());
 	
 	let main = root.items.iter()
 		.find(|item| matches!(item, Item::Proc(pr) if pr.name == "main"));
@@ -54,8 +324,86 @@ pub fn interpret_file(items: Vec<ast::Item>) {
 	
 	// run main procedure, if any
 	if let Some(Item::Proc(pr)) = main {
-		pr.call(Vec::new(), &root).unwrap();
+		pr.invoke(Dir::Fore, Vec::new(), &root, options.transactional)?;
 	} else {
 		eprintln!("No main procedure found.");
 	}
+	
+	Ok(())
+}
+
+/// Creates the built-in function `size`, which gives the length of an array
+/// or string. It's the same as `fn size(seq: _): int = seq.len`.
+fn size_fn() -> Item {
+	let len = LValue {
+		id: "seq".to_string(),
+		ops: vec![Deref::Field("len".to_string())],
+		span: Span::default(),
+	};
+	Item::Fn(Function {
+		name: "size".to_string(),
+		params: vec![("seq".to_string(), Type::Infer)],
+		ret: Type::Int,
+		body: BlockExpr::Expr(Expr::new(ExprKind::LVal(len), Span::default())),
+		span: Span::default(),
+	})
+}
+
+/// Creates a built-in procedure taking a single parameter.
+fn intrinsic_proc(
+	name: &str,
+	(param, mutable, typ): (&str, bool, Type),
+	fore: fn(&mut [Value], &Module) -> EvalResult<()>,
+	back: fn(&mut [Value], &Module) -> EvalResult<()>,
+) -> Item {
+	Item::Proc(Procedure {
+		name: name.to_string(),
+		params: vec![Param {
+			name: param.to_string(),
+			mutable,
+			typ,
+			span: Span::default(),
+		}],
+		code: ProcDef::Internal { fore, back },
+		span: Span::default(),
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use logos::Logos;
+	
+	use super::*;
+	use crate::ast::Parser;
+	use crate::token::Token;
+	
+	/// Bumps the cell `r` points to, then fails.
+	const BUMP_THEN_FAIL: &str = "\
+proc bump(r: ref int)
+	r! += 1
+	do missing
+end
+";
+	
+	/// Calls `bump` on a new cell, returning what's left in the cell.
+	fn bump(transactional: bool) -> Value {
+		let items = Parser::new(Token::lexer(BUMP_THEN_FAIL)).parse_file_module().unwrap();
+		let m = Module::new("test".into(), items);
+		let pr = m.get_proc("bump").unwrap();
+		
+		let cell = heap::alloc(Value::Int(0));
+		let arg = Value::Ref(Type::Int, Some(cell));
+		assert!(pr.invoke(Dir::Fore, vec![arg], &m, transactional).is_err());
+		heap::get(cell).unwrap()
+	}
+	
+	#[test]
+	fn transactional_call_rolls_back() {
+		assert_eq!(bump(true), Value::Int(0));
+	}
+	
+	#[test]
+	fn plain_call_keeps_partial_effects() {
+		assert_eq!(bump(false), Value::Int(1));
+	}
 }
@@ -1,12 +1,15 @@
+use std::convert::TryFrom;
+
 use super::*;
+use super::heap;
 
-use crate::ast::{LValue, Deref};
+use crate::ast::{Deref, Expr, ExprKind, Literal, LValue};
 
 pub type Stack = Vec<StackFrame>;
 
 /// Stores values of parameters and local variables during a function or
 /// procedure call.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct StackFrame {
 	names: Vec<String>,
 	values: Vec<Value>,
@@ -23,6 +26,29 @@ impl StackFrame {
 		&mut self.values
 	}
 	
+	/// Names of the variables in the frame, oldest first.
+	pub fn names(&self) -> &[String] {
+		&self.names
+	}
+	
+	/// The value of the variable `name`, if there is one.
+	pub fn lookup(&self, name: &str) -> Option<&Value> {
+		self.names.iter()
+			.rposition(|var_name| var_name == name)
+			.map(|pos| &self.values[pos])
+	}
+	
+	/// Copies the variables whose names satisfy `keep` into a new frame.
+	pub fn filter<P>(&self, keep: P) -> StackFrame
+	where P: Fn(&str) -> bool {
+		let (names, values) = self.names.iter()
+			.zip(&self.values)
+			.filter(|(name, _)| keep(name))
+			.map(|(name, value)| (name.clone(), value.clone()))
+			.unzip();
+		StackFrame { names, values }
+	}
+	
 	pub fn into_inner(self) -> Vec<Value> {
 		self.values
 	}
@@ -58,90 +84,255 @@ impl StackFrame {
 			.ok_or(EvalError::UnknownIdent(right.to_string()))?;
 		
 		// ensure types are the same
-		assert_eq!(
-			self.values[left_idx].get_type(),
-			self.values[right_idx].get_type(),
-			"tried to swap variables with different types"
-		);
+		let left_type = self.values[left_idx].get_type();
+		let right_type = self.values[right_idx].get_type();
+		if left_type != right_type {
+			return Err(EvalError::TypeMismatch {
+				expected: left_type,
+				got: right_type,
+			});
+		}
 		
 		self.values.swap(left_idx, right_idx);
 		
 		Ok(())
 	}
 	
-	pub fn get(&self, deref_path: &LValue) -> EvalResult<Value> {
+	/// Evaluates the indices of an lvalue, so that it keeps referring to the
+	/// same place even if the variables used in them change.
+	pub fn resolve(&self, deref_path: &LValue, m: &Module) -> EvalResult<LValue> {
+		let mut ops = Vec::with_capacity(deref_path.ops.len());
+		for deref in &deref_path.ops {
+			ops.push(match deref {
+				Deref::Index(expr) => {
+					let lit = Literal::Int(index_number(expr.eval(self, m)?)?);
+					Deref::Index(Expr::new(ExprKind::Lit(lit), expr.span))
+				}
+				deref => deref.clone(),
+			});
+		}
+		
+		Ok(LValue { id: deref_path.id.clone(), ops, span: deref_path.span })
+	}
+	
+	pub fn get(&self, deref_path: &LValue, m: &Module) -> EvalResult<Value> {
 		let pos = self.names.iter()
 			.rposition(|var_name| *var_name == deref_path.id)
 			.ok_or(EvalError::UnknownIdent(deref_path.id.clone()))?;
 		
-		let clone = self.clone();
 		let mut value = self.values[pos].clone();
 		
 		for deref in &deref_path.ops {
 			// TODO move all this into Value.
 			value = match (&value, deref) {
-				// TODO copy (array, index) case from get_mut
 				(Value::Array(arr), Deref::Field(field)) if field == "len" =>
 					Value::Int(arr.len() as i64),
 				
-				(Value::Array(a), Deref::Index(expr)) =>
-					match expr.eval(self)? {
-						Value::Int(i) =>
-							a.get(i as usize).unwrap().clone(),
-						
-						value => todo!("{:?}.({})", a, value),
-					}
+				(Value::Array(a), Deref::Index(expr)) => {
+					let idx = index(expr.eval(self, m)?, a.len())?;
+					a[idx].clone()
+				}
 				
+				// strings are indexed by character, so count those
 				(Value::String(s), Deref::Field(field)) if field == "len" =>
-					Value::Int(s.len() as i64),
+					Value::Int(s.chars().count() as i64),
 				
-				(Value::String(s), Deref::Index(expr)) =>
-					match expr.eval(self)? {
-						Value::Int(i) => {
-							let c = s.chars().nth(i as usize);
-							match c {
-								Some(c) => c.into(),
-								None => panic!("XX str: {:?}, i: {}, len: {}", s, i, s.len()),
-							}
-						}
-						
-						value => todo!("{}.({})", s, value)
+				(Value::Record(_, fields), Deref::Field(field)) =>
+					match fields.iter().find(|(name, _)| name == field) {
+						Some((_, value)) => value.clone(),
+						None => return Err(EvalError::UnknownField {
+							typ: value.get_type(),
+							field: field.clone(),
+						}),
 					}
 				
-				(l, r) => todo!("{} {:?}", l, r)
+				(Value::String(s), Deref::Index(expr)) => {
+					let len = s.chars().count();
+					let idx = index(expr.eval(self, m)?, len)?;
+					s.chars().nth(idx)
+						.map(Value::Char)
+						.ok_or(EvalError::IndexOutOfBounds { index: idx as i64, len })?
+				}
+				
+				(value, Deref::Field(field)) =>
+					return Err(EvalError::UnknownField {
+						typ: value.get_type(),
+						field: field.clone(),
+					}),
+				
+				(value, Deref::Index(_)) =>
+					return Err(EvalError::TypeMismatch {
+						expected: Type::Array(0),
+						got: value.get_type(),
+					}),
+				
+				(Value::Ref(_, Some(cell)), Deref::Direct) =>
+					heap::get(*cell)?,
+				
+				(Value::Ref(_, None), Deref::Direct) =>
+					return Err(EvalError::NullDeref),
+				
+				(value, Deref::Direct) =>
+					return Err(EvalError::TypeMismatch {
+						expected: Type::Ref(Box::new(Type::Infer)),
+						got: value.get_type(),
+					}),
 			};
 		}
 		
-		Ok(value.clone())
+		Ok(value)
 	}
 	
-	pub fn get_mut(&mut self, deref_path: &LValue) -> EvalResult<&mut Value> {
-		let pos = self.names.iter()
-			.rposition(|var_name| *var_name == deref_path.id)
-			.ok_or(EvalError::UnknownIdent(deref_path.id.clone()))?;
+	/// Modifies the value at `deref_path` in place with `f`.
+	///
+	/// This can also modify a character of a string, as long as `f` leaves it
+	/// a character, and follow references into heap cells.
+	pub fn update<F>(&mut self, deref_path: &LValue, m: &Module, f: F) -> EvalResult<()>
+	where F: FnOnce(&mut Value) -> EvalResult<()> {
+		let ops = &deref_path.ops;
 		
-		let clone = self.clone();
-		let mut value = &mut self.values[pos];
+		// everything up to the last `!` only picks which cell to modify
+		let (target, ops) = match ops.iter().rposition(|deref| matches!(deref, Deref::Direct)) {
+			Some(pos) => {
+				let reference = LValue {
+					id: deref_path.id.clone(),
+					ops: ops[..pos].to_vec(),
+					span: deref_path.span,
+				};
+				match self.get(&reference, m)? {
+					Value::Ref(_, Some(cell)) => (Some(cell), &ops[pos + 1..]),
+					Value::Ref(_, None) => return Err(EvalError::NullDeref),
+					value => return Err(EvalError::TypeMismatch {
+						expected: Type::Ref(Box::new(Type::Infer)),
+						got: value.get_type(),
+					}),
+				}
+			}
+			None => (None, &ops[..]),
+		};
 		
-		for deref in &deref_path.ops {
-			match (value, deref) {
-				(Value::Array(array), Deref::Index(expr)) =>
-					match expr.eval(&clone)? {
-						Value::Uint(idx) => {
-							value = &mut array[idx as usize];
-						}
-						Value::Int(idx) => {
-							value = &mut array[idx as usize];
-						}
-						value => return Err(EvalError::TypeMismatch {
-							expected: Type::UInt,
-							got: value.get_type(),
-						}),
-					}
-				_ => todo!()
+		// indices can't be evaluated while part of the frame is borrowed
+		let mut indices = Vec::new();
+		for deref in ops {
+			if let Deref::Index(expr) = deref {
+				indices.push(expr.eval(self, m)?);
 			}
 		}
+		let indices = indices.into_iter();
 		
-		Ok(value)
+		match target {
+			Some(cell) => heap::update(cell, |value| update_in(value, ops, indices, f)),
+			None => {
+				let pos = self.names.iter()
+					.rposition(|var_name| *var_name == deref_path.id)
+					.ok_or_else(|| EvalError::UnknownIdent(deref_path.id.clone()))?;
+				update_in(&mut self.values[pos], ops, indices, f)
+			}
+		}
+	}
+	
+	/// Replaces the value at `deref_path`.
+	pub fn set(&mut self, deref_path: &LValue, m: &Module, val: Value) -> EvalResult<()> {
+		self.update(deref_path, m, |value| {
+			*value = val;
+			Ok(())
+		})
+	}
+}
+
+/// Applies `f` to the part of `value` that `ops` lead to, taking the values
+/// of indices from `indices` in order.
+fn update_in<F, I>(value: &mut Value, ops: &[Deref], mut indices: I, f: F) -> EvalResult<()>
+where
+	F: FnOnce(&mut Value) -> EvalResult<()>,
+	I: Iterator<Item = Value>,
+{
+	let (deref, rest) = match ops.split_first() {
+		Some(split) => split,
+		None => return f(value),
+	};
+	
+	match (value, deref) {
+		(Value::Array(array), Deref::Index(_)) => {
+			let idx = index(indices.next().unwrap_or(Value::Nil), array.len())?;
+			update_in(&mut array[idx], rest, indices, f)
+		}
+		
+		// characters aren't stored as values, so modify a copy and put that
+		// back instead
+		(Value::String(s), Deref::Index(_)) => {
+			let len = s.chars().count();
+			let idx = index(indices.next().unwrap_or(Value::Nil), len)?;
+			let (start, c) = s.char_indices().nth(idx)
+				.ok_or(EvalError::IndexOutOfBounds { index: idx as i64, len })?;
+			
+			let mut value = Value::Char(c);
+			update_in(&mut value, rest, indices, f)?;
+			
+			match value {
+				Value::Char(new) => {
+					s.replace_range(start..start + c.len_utf8(), new.encode_utf8(&mut [0; 4]));
+					Ok(())
+				}
+				value => Err(EvalError::TypeMismatch {
+					expected: Type::Char,
+					got: value.get_type(),
+				}),
+			}
+		}
+		
+		(value, Deref::Index(_)) =>
+			Err(EvalError::TypeMismatch {
+				expected: Type::Array(0),
+				got: value.get_type(),
+			}),
+		
+		(Value::Array(_), Deref::Field(field))
+		| (Value::String(_), Deref::Field(field)) if field == "len" =>
+			Err(EvalError::ReadOnlyField(field.clone())),
+		
+		(value, Deref::Field(field)) =>
+			update_in(field_mut(value, field)?, rest, indices, f),
+		
+		// the caller follows references before getting here
+		(_, Deref::Direct) =>
+			Err(EvalError::Unsupported("nested dereferencing")),
 	}
 }
+
+/// The field called `field` of a record.
+fn field_mut<'a>(value: &'a mut Value, field: &str) -> EvalResult<&'a mut Value> {
+	let typ = value.get_type();
+	match value {
+		Value::Record(_, fields) => fields.iter_mut()
+			.find(|(name, _)| name == field)
+			.map(|(_, value)| value),
+		_ => None,
+	}
+	.ok_or_else(|| EvalError::UnknownField { typ, field: field.to_string() })
+}
+
+/// Converts an index value into a position within a sequence of length `len`.
+fn index(idx: Value, len: usize) -> EvalResult<usize> {
+	let idx = index_number(idx)?;
+	if 0 <= idx && (idx as usize) < len {
+		Ok(idx as usize)
+	} else {
+		Err(EvalError::IndexOutOfBounds { index: idx, len })
+	}
+}
+
+/// The number an index value stands for, which may still be out of bounds.
+fn index_number(idx: Value) -> EvalResult<i64> {
+	Ok(match idx {
+		Value::Int(i) => i,
+		Value::Uint(u) => u as i64,
+		Value::Fixed(t, bits) => t.to_i128(bits) as i64,
+		// anything that doesn't fit is out of bounds anyway
+		Value::BigInt(n) => i64::try_from(&n).unwrap_or(i64::MAX),
+		value => return Err(EvalError::TypeMismatch {
+			expected: Type::UInt,
+			got: value.get_type(),
+		}),
+	})
+}
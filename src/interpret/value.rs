@@ -1,6 +1,11 @@
+use std::convert::TryFrom;
 use std::fmt;
+use std::rc::Rc;
 
-use crate::interpret::{EvalError, EvalResult};
+use num_bigint::BigInt;
+use num_traits::{Pow, Zero};
+
+use crate::interpret::{Eval, EvalError, EvalResult, StackFrame};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Value {
@@ -9,13 +14,41 @@ pub enum Value {
 	//Byte(u8),
 	Int(i64),
 	Uint(u64),
+	/// A fixed-width integer, stored as its bits truncated to the width.
+	Fixed(IntType, u64),
+	BigInt(BigInt),
 	Char(char),
 	String(String),
 	Array(Box<[Value]>),
-	//Proc(Path),
+	/// A reference to a heap cell holding a value of the given type, or a
+	/// null reference if there's no cell.
+	Ref(Type, Option<usize>),
+	/// A function value made by a closure literal.
+	Fn(Rc<Closure>),
+	/// A procedure, by name, along with its signature.
+	Proc(String, Rc<[(bool, Type)]>),
+	/// A value of the named record type, with its fields in the order they
+	/// were declared.
+	Record(String, Box<[(String, Value)]>),
+}
+
+/// A function value, made by evaluating a `fn(a, b): expr` literal.
+///
+/// Two closures are equal if they have the same parameters and body, and
+/// captured the same values.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Closure {
+	/// Names of the parameters, and their types once the closure is given a
+	/// declared type.
+	pub params: Vec<(String, Type)>,
+	pub ret: Type,
+	pub body: Expr,
+	/// Copies of the variables that the body refers to, taken when the
+	/// closure was made.
+	pub env: StackFrame,
 }
 
-use crate::ast::Type;
+use crate::ast::{BinOp, Expr, IntType, Module, Type};
 impl Value {
 	pub fn get_type(&self) -> Type {
 		match self {
@@ -23,13 +56,46 @@ impl Value {
 			Value::Bool(_)   => Type::Bool,
 			Value::Int(_)    => Type::Int,
 			Value::Uint(_)   => Type::UInt,
+			Value::Fixed(t, _) => Type::Fixed(*t),
+			Value::BigInt(_) => Type::BigInt,
 			Value::Char(_)   => Type::Char,
 			Value::String(_) => Type::String,
 			
-			Value::Array(_)  => todo!()
+			Value::Array(a)  => Type::Array(a.len()),
+			Value::Ref(t, _) => Type::Ref(Box::new(t.clone())),
+			Value::Fn(closure) => Type::Fn(
+				closure.params.iter().map(|(_, typ)| typ.clone()).collect(),
+				Box::new(closure.ret.clone()),
+			),
+			Value::Proc(_, signature) => Type::Proc(signature.to_vec()),
+			Value::Record(name, _) => Type::Record(name.clone()),
 		}
 	}
 	
+	/// The value a variable of the given type starts with when declared
+	/// without an initializer, if the type has one. A record's zero value
+	/// holds the zero value of each of its fields, as declared in `m`.
+	pub fn zero(typ: &Type, m: &Module) -> Option<Value> {
+		Some(match typ {
+			Type::Unit   => Value::Nil,
+			Type::Bool   => Value::Bool(false),
+			Type::Int    => Value::Int(0),
+			Type::UInt   => Value::Uint(0),
+			Type::Fixed(t) => Value::Fixed(*t, 0),
+			Type::BigInt => Value::BigInt(BigInt::zero()),
+			Type::Char   => Value::Char('\0'),
+			Type::String => Value::String(String::new()),
+			Type::Ref(t) => Value::Ref((**t).clone(), None),
+			Type::Record(name) => {
+				let fields = m.get_record(name)?.fields.iter()
+					.map(|(field, typ)| Some((field.clone(), Value::zero(typ, m)?)))
+					.collect::<Option<_>>()?;
+				Value::Record(name.clone(), fields)
+			}
+			_ => return None,
+		})
+	}
+	
 	pub fn swap(&mut self, val: &mut Value) -> EvalResult<()> {
 		// check that types are the same.
 		if self.get_type() != val.get_type() {
@@ -43,6 +109,51 @@ impl Value {
 		Ok(())
 	}
 	
+	/// Converts an integer to the type `typ`, so that e.g. `5` can be used
+	/// where a `u32` or `uint` is expected. An integer that doesn't fit in `typ` is
+	/// an error. Other values are returned unchanged.
+	pub fn coerce(self, typ: &Type) -> EvalResult<Value> {
+		let n = match self {
+			Value::Int(n) => n as i128,
+			Value::Uint(n) => n as i128,
+			Value::Fn(closure) => return Ok(match typ {
+				Type::Fn(params, ret) if params.len() == closure.params.len() =>
+					Value::Fn(Rc::new(closure.with_type(params, ret))),
+				_ => Value::Fn(closure),
+			}),
+			val => return Ok(val),
+		};
+		
+		let out_of_range = || EvalError::IntOutOfRange { value: self.clone(), typ: typ.clone() };
+		match typ {
+			Type::Fixed(t) => t.checked_bits(n)
+				.map(|bits| Value::Fixed(*t, bits))
+				.ok_or_else(out_of_range),
+			Type::BigInt => Ok(Value::BigInt(n.into())),
+			Type::Int => i64::try_from(n)
+				.map(Value::Int)
+				.map_err(|_| out_of_range()),
+			Type::UInt => u64::try_from(n)
+				.map(Value::Uint)
+				.map_err(|_| out_of_range()),
+			_ => Ok(self),
+		}
+	}
+	
+	/// Coerces the value to `typ`, then makes sure it has that type, unless
+	/// `typ` is left to be inferred.
+	pub fn conform(self, typ: &Type) -> EvalResult<Value> {
+		let val = self.coerce(typ)?;
+		let got = val.get_type();
+		if *typ != Type::Infer && got != *typ {
+			return Err(EvalError::TypeMismatch {
+				expected: typ.clone(),
+				got,
+			});
+		}
+		Ok(val)
+	}
+	
 	pub fn xor(&mut self, val: &Value) -> EvalResult<()> {
 		match (self, val) {
 			(Value::Nil, Value::Nil) => {}
@@ -51,10 +162,500 @@ impl Value {
 			
 			(Value::Int(a), Value::Int(b)) => *a ^= b,
 			
-			_ => todo!()
+			(Value::Uint(a), Value::Uint(b)) => *a ^= b,
+			
+			(Value::Fixed(t, a), b) => *a ^= t.operand(b)?,
+			
+			(Value::BigInt(a), b) => *a ^= big_operand(b)?,
+			
+			(a, b) => return Err(update_mismatch(a, b)),
+		}
+		Ok(())
+	}
+	
+	/// Implements `+=`. If `checked` is set, `int` and `uint` overflow is an
+	/// error instead of wrapping around.
+	pub fn add(&mut self, val: &Value, checked: bool) -> EvalResult<()> {
+		match (self, val) {
+			(Value::Int(a), Value::Int(b)) => *a = overflow(checked, a.checked_add(*b), a.wrapping_add(*b))?,
+			(Value::Uint(a), Value::Uint(b)) => *a = overflow(checked, a.checked_add(*b), a.wrapping_add(*b))?,
+			(Value::Fixed(t, a), b) => *a = t.wrap(a.wrapping_add(t.operand(b)?)),
+			(Value::BigInt(a), b) => *a += big_operand(b)?,
+			(Value::Char(c), Value::Int(by)) => shift_char(c, *by)?,
+			(a, b) => return Err(update_mismatch(a, b)),
 		}
 		Ok(())
 	}
+	
+	/// Implements `-=`. Overflow is handled like in [`Value::add`].
+	pub fn sub(&mut self, val: &Value, checked: bool) -> EvalResult<()> {
+		match (self, val) {
+			(Value::Int(a), Value::Int(b)) => *a = overflow(checked, a.checked_sub(*b), a.wrapping_sub(*b))?,
+			(Value::Uint(a), Value::Uint(b)) => *a = overflow(checked, a.checked_sub(*b), a.wrapping_sub(*b))?,
+			(Value::Fixed(t, a), b) => *a = t.wrap(a.wrapping_sub(t.operand(b)?)),
+			(Value::BigInt(a), b) => *a -= big_operand(b)?,
+			(Value::Char(c), Value::Int(by)) => shift_char(c, by.wrapping_neg())?,
+			(a, b) => return Err(update_mismatch(a, b)),
+		}
+		Ok(())
+	}
+	
+	/// Implements `:<`. Fixed-width integers rotate at their own width.
+	pub fn rotate_left(&mut self, val: &Value) -> EvalResult<()> {
+		let by = rotation(self, val)?;
+		match self {
+			Value::Int(a) => *a = a.rotate_left(by),
+			Value::Uint(a) => *a = a.rotate_left(by),
+			Value::Fixed(t, a) => *a = t.rotate_left(*a, by.into()),
+			a => return Err(update_mismatch(a, val)),
+		}
+		Ok(())
+	}
+	
+	/// Implements `:>`. Fixed-width integers rotate at their own width.
+	pub fn rotate_right(&mut self, val: &Value) -> EvalResult<()> {
+		let by = rotation(self, val)?;
+		match self {
+			Value::Int(a) => *a = a.rotate_right(by),
+			Value::Uint(a) => *a = a.rotate_right(by),
+			Value::Fixed(t, a) => *a = t.rotate_left(*a, -i64::from(by)),
+			a => return Err(update_mismatch(a, val)),
+		}
+		Ok(())
+	}
+	
+	/// Applies a binary operator to two integers where at least one is
+	/// fixed-width. Arithmetic wraps around at the width of type `t`.
+	pub fn fixed_bin_op(op: BinOp, t: IntType, left: &Value, right: &Value) -> EvalResult<Value> {
+		let invalid = || EvalError::InvalidOperands {
+			op,
+			left: left.get_type(),
+			right: right.get_type(),
+		};
+		let l = t.operand(left).map_err(|_| invalid())?;
+		let r = t.operand(right).map_err(|_| invalid())?;
+		let (sl, sr) = (t.to_i128(l), t.to_i128(r));
+		
+		let fixed = |bits: u64| Ok(Value::Fixed(t, t.wrap(bits)));
+		// comparisons use the numbers the operands stand for, so a constant
+		// out of the type's range isn't wrapped into it first
+		let compare = |f: fn(&i128, &i128) -> bool| {
+			let l = t.exact(left).map_err(|_| invalid())?;
+			let r = t.exact(right).map_err(|_| invalid())?;
+			Ok(Value::Bool(f(&l, &r)))
+		};
+		match op {
+			BinOp::Exp => fixed(wrapping_pow(l, r)),
+			BinOp::Shl => fixed(l.checked_shl(shift_by(sr)?).unwrap_or(0)),
+			// shifting right fills with the sign bit of signed types
+			BinOp::Shr => fixed((sl >> shift_by(sr)?.min(127)) as u64),
+			BinOp::Mul => fixed(l.wrapping_mul(r)),
+			BinOp::Add => fixed(l.wrapping_add(r)),
+			BinOp::Sub => fixed(l.wrapping_sub(r)),
+			BinOp::Div | BinOp::Mod if r == 0 => Err(EvalError::DivByZero),
+			BinOp::Div => fixed((sl / sr) as u64),
+			BinOp::Mod => fixed((((sl % sr) + sr) % sr) as u64),
+			BinOp::BitAnd => fixed(l & r),
+			BinOp::BitOr => fixed(l | r),
+			BinOp::Xor => fixed(l ^ r),
+			BinOp::Eq => compare(i128::eq),
+			BinOp::Ne => compare(i128::ne),
+			BinOp::Lt => compare(i128::lt),
+			BinOp::Gt => compare(i128::gt),
+			BinOp::Le => compare(i128::le),
+			BinOp::Ge => compare(i128::ge),
+			BinOp::And | BinOp::Or => Err(invalid()),
+		}
+	}
+}
+
+impl Value {
+	/// Applies a binary operator to two `int`s. If `checked` is set, overflow
+	/// is an error instead of wrapping around.
+	pub fn int_bin_op(op: BinOp, l: i64, r: i64, checked: bool) -> EvalResult<Value> {
+		let int = |exact: Option<i64>, wrapped: i64| Ok(Value::Int(overflow(checked, exact, wrapped)?));
+		match op {
+			BinOp::Exp if r < 0 => Err(EvalError::NegativeExponent(r)),
+			BinOp::Exp => int(
+				u32::try_from(r).ok().and_then(|r| l.checked_pow(r)),
+				wrapping_pow(l as u64, r as u64) as i64,
+			),
+			// shifts drop the bits shifted out, so they never overflow
+			BinOp::Shl => Ok(Value::Int(l.checked_shl(shift_by(r.into())?).unwrap_or(0))),
+			BinOp::Shr => Ok(Value::Int(l.checked_shr(shift_by(r.into())?).unwrap_or(l >> 63))),
+			BinOp::Mul => int(l.checked_mul(r), l.wrapping_mul(r)),
+			BinOp::Add => int(l.checked_add(r), l.wrapping_add(r)),
+			BinOp::Sub => int(l.checked_sub(r), l.wrapping_sub(r)),
+			BinOp::Div | BinOp::Mod if r == 0 => Err(EvalError::DivByZero),
+			BinOp::Div => int(l.checked_div(r), l.wrapping_div(r)),
+			// the result has the sign of the divisor, and can't overflow
+			BinOp::Mod => {
+				let m = l.wrapping_rem(r);
+				Ok(Value::Int(if m != 0 && (m < 0) != (r < 0) { m + r } else { m }))
+			}
+			BinOp::BitAnd => Ok(Value::Int(l & r)),
+			BinOp::BitOr => Ok(Value::Int(l | r)),
+			BinOp::Xor => Ok(Value::Int(l ^ r)),
+			BinOp::Eq => Ok(Value::Bool(l == r)),
+			BinOp::Ne => Ok(Value::Bool(l != r)),
+			BinOp::Lt => Ok(Value::Bool(l < r)),
+			BinOp::Gt => Ok(Value::Bool(l > r)),
+			BinOp::Le => Ok(Value::Bool(l <= r)),
+			BinOp::Ge => Ok(Value::Bool(l >= r)),
+			BinOp::And | BinOp::Or => Err(EvalError::InvalidOperands {
+				op,
+				left: Type::Int,
+				right: Type::Int,
+			}),
+		}
+	}
+	
+	/// Applies a binary operator to two `uint`s. Overflow is handled like in
+	/// [`Value::int_bin_op`].
+	pub fn uint_bin_op(op: BinOp, l: u64, r: u64, checked: bool) -> EvalResult<Value> {
+		let uint = |exact: Option<u64>, wrapped: u64| Ok(Value::Uint(overflow(checked, exact, wrapped)?));
+		match op {
+			BinOp::Exp => uint(
+				u32::try_from(r).ok().and_then(|r| l.checked_pow(r)),
+				wrapping_pow(l, r),
+			),
+			BinOp::Shl => Ok(Value::Uint(l.checked_shl(shift_by(r.into())?).unwrap_or(0))),
+			BinOp::Shr => Ok(Value::Uint(l.checked_shr(shift_by(r.into())?).unwrap_or(0))),
+			BinOp::Mul => uint(l.checked_mul(r), l.wrapping_mul(r)),
+			BinOp::Add => uint(l.checked_add(r), l.wrapping_add(r)),
+			BinOp::Sub => uint(l.checked_sub(r), l.wrapping_sub(r)),
+			BinOp::Div | BinOp::Mod if r == 0 => Err(EvalError::DivByZero),
+			BinOp::Div => Ok(Value::Uint(l / r)),
+			BinOp::Mod => Ok(Value::Uint(l % r)),
+			BinOp::BitAnd => Ok(Value::Uint(l & r)),
+			BinOp::BitOr => Ok(Value::Uint(l | r)),
+			BinOp::Xor => Ok(Value::Uint(l ^ r)),
+			BinOp::Eq => Ok(Value::Bool(l == r)),
+			BinOp::Ne => Ok(Value::Bool(l != r)),
+			BinOp::Lt => Ok(Value::Bool(l < r)),
+			BinOp::Gt => Ok(Value::Bool(l > r)),
+			BinOp::Le => Ok(Value::Bool(l <= r)),
+			BinOp::Ge => Ok(Value::Bool(l >= r)),
+			BinOp::And | BinOp::Or => Err(EvalError::InvalidOperands {
+				op,
+				left: Type::UInt,
+				right: Type::UInt,
+			}),
+		}
+	}
+	
+	/// Applies a binary operator to a `uint` and an `int`, in either order.
+	/// Arithmetic and comparisons use the exact values of both, so `u + -1`
+	/// is `u - 1` and `u > -1` always holds, and the result of arithmetic has
+	/// to fit in a `uint`. Overflow is handled like in [`Value::int_bin_op`].
+	///
+	/// Bitwise operators use the bits of the `int`, and shifts keep the type
+	/// of the value being shifted.
+	pub fn mixed_bin_op(op: BinOp, left: &Value, right: &Value, checked: bool) -> EvalResult<Value> {
+		let invalid = || EvalError::InvalidOperands {
+			op,
+			left: left.get_type(),
+			right: right.get_type(),
+		};
+		let exact = |val: &Value| match *val {
+			Value::Int(n) => Ok(i128::from(n)),
+			Value::Uint(n) => Ok(i128::from(n)),
+			_ => Err(invalid()),
+		};
+		let (l, r) = (exact(left)?, exact(right)?);
+		
+		let uint = |exact: i128, wrapped: u64| {
+			let exact = u64::try_from(exact).ok();
+			Ok(Value::Uint(overflow(checked, exact, wrapped)?))
+		};
+		match op {
+			BinOp::Shl | BinOp::Shr => match *left {
+				Value::Int(l) => Value::int_bin_op(op, l, i64::try_from(r).unwrap_or(i64::MAX), checked),
+				_ => Value::uint_bin_op(op, l as u64, u64::try_from(r).map_err(|_| EvalError::InvalidShift(r))?, checked),
+			},
+			BinOp::Exp if r < 0 => Err(EvalError::NegativeExponent(r as i64)),
+			BinOp::Exp => {
+				let wrapped = wrapping_pow(l as u64, r as u64);
+				match u32::try_from(r).ok().and_then(|r| l.checked_pow(r)) {
+					Some(n) => uint(n, wrapped),
+					None => Ok(Value::Uint(overflow(checked, None, wrapped)?)),
+				}
+			}
+			// none of these can overflow an `i128`
+			BinOp::Mul => uint(l * r, (l * r) as u64),
+			BinOp::Add => uint(l + r, (l + r) as u64),
+			BinOp::Sub => uint(l - r, (l - r) as u64),
+			BinOp::Div | BinOp::Mod if r == 0 => Err(EvalError::DivByZero),
+			BinOp::Div => uint(l / r, (l / r) as u64),
+			// the result has the sign of the divisor, like for `int`s
+			BinOp::Mod => {
+				let m = l % r;
+				let m = if m != 0 && (m < 0) != (r < 0) { m + r } else { m };
+				uint(m, m as u64)
+			}
+			BinOp::BitAnd => Ok(Value::Uint(l as u64 & r as u64)),
+			BinOp::BitOr => Ok(Value::Uint(l as u64 | r as u64)),
+			BinOp::Xor => Ok(Value::Uint(l as u64 ^ r as u64)),
+			BinOp::Eq => Ok(Value::Bool(l == r)),
+			BinOp::Ne => Ok(Value::Bool(l != r)),
+			BinOp::Lt => Ok(Value::Bool(l < r)),
+			BinOp::Gt => Ok(Value::Bool(l > r)),
+			BinOp::Le => Ok(Value::Bool(l <= r)),
+			BinOp::Ge => Ok(Value::Bool(l >= r)),
+			BinOp::And | BinOp::Or => Err(invalid()),
+		}
+	}
+	
+	/// Negates an `int`. Overflow is handled like in [`Value::int_bin_op`].
+	pub fn int_neg(n: i64, checked: bool) -> EvalResult<Value> {
+		Ok(Value::Int(overflow(checked, n.checked_neg(), n.wrapping_neg())?))
+	}
+}
+
+impl Value {
+	/// Applies a binary operator to two integers where at least one is a
+	/// `bigint`. The result is exact, so nothing can overflow.
+	pub fn big_bin_op(op: BinOp, left: &Value, right: &Value) -> EvalResult<Value> {
+		let invalid = || EvalError::InvalidOperands {
+			op,
+			left: left.get_type(),
+			right: right.get_type(),
+		};
+		let l = big_operand(left).map_err(|_| invalid())?;
+		let r = big_operand(right).map_err(|_| invalid())?;
+		
+		Ok(match op {
+			BinOp::Exp => {
+				let exp = u32::try_from(&r).map_err(|_| invalid())?;
+				Value::BigInt(Pow::pow(l, exp))
+			}
+			BinOp::Shl | BinOp::Shr => {
+				let by = i128::try_from(&r).map_err(|_| invalid())?;
+				let by = shift_by(by)? as usize;
+				Value::BigInt(if op == BinOp::Shl { l << by } else { l >> by })
+			}
+			BinOp::Mul => Value::BigInt(l * r),
+			BinOp::Add => Value::BigInt(l + r),
+			BinOp::Sub => Value::BigInt(l - r),
+			BinOp::Div | BinOp::Mod if r.is_zero() => return Err(EvalError::DivByZero),
+			BinOp::Div => Value::BigInt(l / r),
+			// the result has the sign of the divisor, like with `int`
+			BinOp::Mod => Value::BigInt(((l % &r) + &r) % r),
+			BinOp::BitAnd => Value::BigInt(l & r),
+			BinOp::BitOr => Value::BigInt(l | r),
+			BinOp::Xor => Value::BigInt(l ^ r),
+			BinOp::Eq => Value::Bool(l == r),
+			BinOp::Ne => Value::Bool(l != r),
+			BinOp::Lt => Value::Bool(l < r),
+			BinOp::Gt => Value::Bool(l > r),
+			BinOp::Le => Value::Bool(l <= r),
+			BinOp::Ge => Value::Bool(l >= r),
+			BinOp::And | BinOp::Or => return Err(invalid()),
+		})
+	}
+}
+
+/// Converts the right-hand side of an operation on a `bigint`.
+fn big_operand(val: &Value) -> EvalResult<BigInt> {
+	match val {
+		Value::BigInt(n) => Ok(n.clone()),
+		Value::Int(n) => Ok((*n).into()),
+		Value::Uint(n) => Ok((*n).into()),
+		val => Err(EvalError::TypeMismatch {
+			expected: Type::BigInt,
+			got: val.get_type(),
+		}),
+	}
+}
+
+impl IntType {
+	fn mask(self) -> u64 {
+		u64::MAX >> (64 - self.bits())
+	}
+	
+	/// Truncates bits to the width of the type.
+	pub fn wrap(self, bits: u64) -> u64 {
+		bits & self.mask()
+	}
+	
+	/// The number that the bits of a value of this type represent.
+	pub fn to_i128(self, bits: u64) -> i128 {
+		let shift = 128 - self.bits();
+		if self.is_signed() {
+			// sign-extend from the top bit of the type
+			((bits as i128) << shift) >> shift
+		} else {
+			bits as i128
+		}
+	}
+	
+	/// The bits of a value of this type representing `n`, if it's in range.
+	pub fn checked_bits(self, n: i128) -> Option<u64> {
+		let bits = self.wrap(n as u64);
+		if self.to_i128(bits) == n {
+			Some(bits)
+		} else {
+			None
+		}
+	}
+	
+	/// Bits of the right-hand side of an operation on this type. Plain
+	/// integers wrap around to fit, so constants like `0x9e3779b9` can be
+	/// used directly.
+	fn operand(self, val: &Value) -> EvalResult<u64> {
+		match val {
+			Value::Fixed(t, bits) if *t == self => Ok(*bits),
+			Value::Int(n) => Ok(self.wrap(*n as u64)),
+			Value::Uint(n) => Ok(self.wrap(*n)),
+			val => Err(EvalError::TypeMismatch {
+				expected: Type::Fixed(self),
+				got: val.get_type(),
+			}),
+		}
+	}
+	
+	/// The number that the right-hand side of a comparison with this type
+	/// stands for. Unlike with [`IntType::operand`], plain integers aren't
+	/// wrapped, so a `u8` holding 50 isn't equal to 306.
+	fn exact(self, val: &Value) -> EvalResult<i128> {
+		match val {
+			Value::Fixed(t, bits) if *t == self => Ok(self.to_i128(*bits)),
+			Value::Int(n) => Ok((*n).into()),
+			Value::Uint(n) => Ok((*n).into()),
+			val => Err(EvalError::TypeMismatch {
+				expected: Type::Fixed(self),
+				got: val.get_type(),
+			}),
+		}
+	}
+	
+	/// Rotates the bits of a value of this type left by `by`, or right if
+	/// it's negative.
+	fn rotate_left(self, bits: u64, by: i64) -> u64 {
+		let width = self.bits() as i64;
+		let by = by.rem_euclid(width) as u32;
+		if by == 0 {
+			bits
+		} else {
+			self.wrap((bits << by) | (bits >> (self.bits() - by)))
+		}
+	}
+}
+
+/// The result of an `int` or `uint` operation, given its `exact` version
+/// that's `None` on overflow and its `wrapped` version. If `checked` is set,
+/// overflow is an error; otherwise the result wraps around.
+///
+/// Fixed-width integers always wrap, and `bigint`s never overflow.
+fn overflow<T>(checked: bool, exact: Option<T>, wrapped: T) -> EvalResult<T> {
+	if checked {
+		exact.ok_or(EvalError::Overflow)
+	} else {
+		Ok(wrapped)
+	}
+}
+
+/// Raises `base` to the power `exp`, wrapping around at 64 bits.
+fn wrapping_pow(mut base: u64, mut exp: u64) -> u64 {
+	let mut acc: u64 = 1;
+	while exp > 0 {
+		if exp & 1 == 1 {
+			acc = acc.wrapping_mul(base);
+		}
+		base = base.wrapping_mul(base);
+		exp >>= 1;
+	}
+	acc
+}
+
+impl Closure {
+	/// Makes a closure from a literal, capturing the variables of `t` that
+	/// its body uses.
+	pub fn new(params: &[String], body: &Expr, t: &StackFrame) -> Self {
+		let env = t.filter(|name| {
+			!params.iter().any(|param| param == name) && body.mentions(name)
+		});
+		Closure {
+			params: params.iter()
+				.map(|param| (param.clone(), Type::Infer))
+				.collect(),
+			ret: Type::Infer,
+			body: body.clone(),
+			env,
+		}
+	}
+	
+	/// A copy of the closure whose parameter and return types are given by a
+	/// `fn` type, wherever they aren't already known.
+	fn with_type(&self, params: &[Type], ret: &Type) -> Self {
+		let fill = |old: &Type, new: &Type| match old {
+			Type::Infer => new.clone(),
+			old => old.clone(),
+		};
+		Closure {
+			params: self.params.iter().zip(params)
+				.map(|((name, old), new)| (name.clone(), fill(old, new)))
+				.collect(),
+			ret: fill(&self.ret, ret),
+			body: self.body.clone(),
+			env: self.env.clone(),
+		}
+	}
+	
+	/// Evaluates the body with the parameters bound to `args`. `name` is what
+	/// the closure was called through, for error messages.
+	pub fn call(&self, name: &str, args: Vec<Value>, m: &Module) -> EvalResult<Value> {
+		if args.len() != self.params.len() {
+			return Err(EvalError::ArityMismatch {
+				name: name.to_string(),
+				expected: self.params.len(),
+				got: args.len(),
+			});
+		}
+		
+		let mut env = self.env.clone();
+		for ((param, typ), arg) in self.params.iter().zip(args) {
+			env.push(param.clone(), arg.conform(typ)?);
+		}
+		
+		self.body.eval(&env, m)?.conform(&self.ret)
+	}
+}
+
+/// Number of bits to shift by for `<<` or `>>`, which must not be negative.
+fn shift_by(by: i128) -> EvalResult<u32> {
+	u32::try_from(by).map_err(|_| EvalError::InvalidShift(by))
+}
+
+/// The amount to rotate `left` by, which can be any fixed-size integer. It's
+/// reduced modulo 64, which keeps its meaning for every type since all of
+/// their widths divide 64.
+fn rotation(left: &Value, by: &Value) -> EvalResult<u32> {
+	let by = match by {
+		Value::Int(n) => i128::from(*n),
+		Value::Uint(n) => i128::from(*n),
+		Value::Fixed(t, bits) => t.to_i128(*bits),
+		by => return Err(update_mismatch(left, by)),
+	};
+	Ok(by.rem_euclid(64) as u32)
+}
+
+/// Error for an update whose operands have types it can't handle.
+fn update_mismatch(left: &Value, right: &Value) -> EvalError {
+	EvalError::TypeMismatch {
+		expected: left.get_type(),
+		got: right.get_type(),
+	}
+}
+
+/// Moves a character `by` code points, as long as it stays a valid character.
+fn shift_char(c: &mut char, by: i64) -> EvalResult<()> {
+	let code = (*c as i64).wrapping_add(by);
+	*c = Some(code)
+		.filter(|code| (0..=u32::MAX as i64).contains(code))
+		.and_then(|code| char::from_u32(code as u32))
+		.ok_or(EvalError::InvalidCharCode(code))?;
+	Ok(())
 }
 
 impl fmt::Display for Value {
@@ -65,6 +666,8 @@ impl fmt::Display for Value {
 			Value::Bool(b) => b.fmt(fmt),
 			Value::Int(i)  => i.fmt(fmt),
 			Value::Uint(u) => u.fmt(fmt),
+			Value::Fixed(t, bits) => t.to_i128(*bits).fmt(fmt),
+			Value::BigInt(n) => n.fmt(fmt),
 			
 			Value::Char(c)   => write!(fmt, "{:?}", c),
 			Value::String(s) => write!(fmt, "{:?}", s),
@@ -76,6 +679,25 @@ impl fmt::Display for Value {
 				}
 				fmt.write_str("]")
 			}
+			
+			Value::Ref(_, None) => fmt.write_str("null"),
+			Value::Ref(_, Some(cell)) => write!(fmt, "ref #{}", cell),
+			
+			Value::Fn(closure) => {
+				let params: Vec<&str> = closure.params.iter()
+					.map(|(name, _)| name.as_str())
+					.collect();
+				write!(fmt, "fn({})", params.join(", "))
+			}
+			Value::Proc(name, _) => write!(fmt, "proc {}", name),
+			
+			Value::Record(name, fields) => {
+				write!(fmt, "{} {{ ", name)?;
+				for (field, value) in fields.iter() {
+					write!(fmt, "{}: {}, ", field, value)?;
+				}
+				fmt.write_str("}")
+			}
 		}
 	}
 }
@@ -104,3 +726,158 @@ impl From<String> for Value {
 	#[inline]
 	fn from(s: String) -> Self { Value::String(s) }
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	#[test]
+	fn int_overflow_wraps() {
+		assert_eq!(Value::int_bin_op(BinOp::Add, i64::MAX, 1, false).unwrap(), Value::Int(i64::MIN));
+		assert_eq!(Value::int_bin_op(BinOp::Mul, i64::MIN, -1, false).unwrap(), Value::Int(i64::MIN));
+		assert_eq!(Value::int_bin_op(BinOp::Exp, 2, 64, false).unwrap(), Value::Int(0));
+		assert_eq!(Value::uint_bin_op(BinOp::Sub, 0, 1, false).unwrap(), Value::Uint(u64::MAX));
+		assert_eq!(Value::int_neg(i64::MIN, false).unwrap(), Value::Int(i64::MIN));
+		
+		let mut n = Value::Uint(u64::MAX);
+		n.add(&Value::Uint(2), false).unwrap();
+		assert_eq!(n, Value::Uint(1));
+		n.sub(&Value::Uint(2), false).unwrap();
+		assert_eq!(n, Value::Uint(u64::MAX));
+	}
+	
+	#[test]
+	fn int_overflow_checked() {
+		assert!(matches!(Value::int_bin_op(BinOp::Add, i64::MAX, 1, true), Err(EvalError::Overflow)));
+		assert!(matches!(Value::int_bin_op(BinOp::Div, i64::MIN, -1, true), Err(EvalError::Overflow)));
+		assert!(matches!(Value::int_bin_op(BinOp::Exp, 2, 64, true), Err(EvalError::Overflow)));
+		assert!(matches!(Value::uint_bin_op(BinOp::Sub, 0, 1, true), Err(EvalError::Overflow)));
+		assert!(matches!(Value::int_neg(i64::MIN, true), Err(EvalError::Overflow)));
+		
+		let mut n = Value::Int(i64::MIN);
+		assert!(matches!(n.sub(&Value::Int(1), true), Err(EvalError::Overflow)));
+		assert_eq!(n, Value::Int(i64::MIN));
+	}
+	
+	#[test]
+	fn in_range_unaffected_by_checking() {
+		for &checked in &[false, true] {
+			assert_eq!(Value::int_bin_op(BinOp::Sub, 1, 3, checked).unwrap(), Value::Int(-2));
+			assert_eq!(Value::uint_bin_op(BinOp::Mul, 6, 7, checked).unwrap(), Value::Uint(42));
+			assert_eq!(Value::int_bin_op(BinOp::Shl, 1, 64, checked).unwrap(), Value::Int(0));
+		}
+	}
+	
+	#[test]
+	fn fixed_width_always_wraps() {
+		let mut n = Value::Fixed(IntType::U8, 255);
+		n.add(&Value::Int(1), true).unwrap();
+		assert_eq!(n, Value::Fixed(IntType::U8, 0));
+	}
+	
+	#[test]
+	fn rotate_by_any_integer_type() {
+		let mut n = Value::Fixed(IntType::U8, 0b1000_0001);
+		n.rotate_left(&Value::Fixed(IntType::U8, 1)).unwrap();
+		assert_eq!(n, Value::Fixed(IntType::U8, 0b0000_0011));
+		n.rotate_right(&Value::Uint(1)).unwrap();
+		assert_eq!(n, Value::Fixed(IntType::U8, 0b1000_0001));
+		n.rotate_left(&Value::Int(9)).unwrap();
+		assert_eq!(n, Value::Fixed(IntType::U8, 0b0000_0011));
+		
+		// a negative amount rotates the other way
+		let mut n = Value::Int(1);
+		n.rotate_right(&Value::Fixed(IntType::I32, 0xffff_ffff)).unwrap();
+		assert_eq!(n, Value::Int(2));
+		
+		let mut n = Value::Uint(1);
+		n.rotate_left(&Value::Int(-1)).unwrap();
+		assert_eq!(n, Value::Uint(1 << 63));
+		n.rotate_right(&Value::Uint(u64::MAX)).unwrap();
+		assert_eq!(n, Value::Uint(1));
+	}
+	
+	#[test]
+	fn rotate_by_non_integer() {
+		let mut n = Value::Int(1);
+		assert!(matches!(
+			n.rotate_left(&Value::String("1".into())),
+			Err(EvalError::TypeMismatch { .. })
+		));
+	}
+	
+	fn fixed(t: IntType, n: i128) -> Value {
+		Value::Fixed(t, t.checked_bits(n).unwrap())
+	}
+	
+	#[test]
+	fn fixed_comparison_is_exact() {
+		let w = fixed(IntType::U8, 50);
+		let cmp = |op, n: i64| Value::fixed_bin_op(op, IntType::U8, &w, &Value::Int(n)).unwrap();
+		assert_eq!(cmp(BinOp::Eq, 50), Value::Bool(true));
+		assert_eq!(cmp(BinOp::Eq, 306), Value::Bool(false));
+		assert_eq!(cmp(BinOp::Ne, 306), Value::Bool(true));
+		assert_eq!(cmp(BinOp::Lt, 300), Value::Bool(true));
+		assert_eq!(cmp(BinOp::Le, 50), Value::Bool(true));
+		assert_eq!(cmp(BinOp::Gt, -1), Value::Bool(true));
+		assert_eq!(cmp(BinOp::Ge, 51), Value::Bool(false));
+		
+		let b = fixed(IntType::I8, -1);
+		let cmp = |op, n: i64| Value::fixed_bin_op(op, IntType::I8, &Value::Int(n), &b).unwrap();
+		assert_eq!(cmp(BinOp::Eq, -1), Value::Bool(true));
+		assert_eq!(cmp(BinOp::Eq, 255), Value::Bool(false));
+		assert_eq!(cmp(BinOp::Gt, 255), Value::Bool(true));
+	}
+	
+	#[test]
+	fn fixed_arithmetic_wraps_constants() {
+		let w = fixed(IntType::U8, 50);
+		let sum = Value::fixed_bin_op(BinOp::Add, IntType::U8, &w, &Value::Int(306)).unwrap();
+		assert_eq!(sum, fixed(IntType::U8, 100));
+		let diff = Value::fixed_bin_op(BinOp::Sub, IntType::U8, &w, &Value::Uint(51)).unwrap();
+		assert_eq!(diff, fixed(IntType::U8, 255));
+	}
+	
+	fn mixed(op: BinOp, left: Value, right: Value, checked: bool) -> EvalResult<Value> {
+		Value::mixed_bin_op(op, &left, &right, checked)
+	}
+	
+	#[test]
+	fn mixed_comparison_is_exact() {
+		assert_eq!(mixed(BinOp::Gt, Value::Uint(5), Value::Int(-1), false).unwrap(), Value::Bool(true));
+		assert_eq!(mixed(BinOp::Lt, Value::Int(-1), Value::Uint(0), false).unwrap(), Value::Bool(true));
+		assert_eq!(mixed(BinOp::Eq, Value::Uint(u64::MAX), Value::Int(-1), false).unwrap(), Value::Bool(false));
+		assert_eq!(mixed(BinOp::Eq, Value::Int(7), Value::Uint(7), false).unwrap(), Value::Bool(true));
+		assert_eq!(mixed(BinOp::Ge, Value::Uint(1 << 63), Value::Int(i64::MAX), false).unwrap(), Value::Bool(true));
+	}
+	
+	#[test]
+	fn mixed_arithmetic_is_exact() {
+		for &checked in &[false, true] {
+			assert_eq!(mixed(BinOp::Add, Value::Uint(5), Value::Int(-1), checked).unwrap(), Value::Uint(4));
+			assert_eq!(mixed(BinOp::Sub, Value::Uint(5), Value::Int(-1), checked).unwrap(), Value::Uint(6));
+			assert_eq!(mixed(BinOp::Add, Value::Int(-3), Value::Uint(5), checked).unwrap(), Value::Uint(2));
+			assert_eq!(mixed(BinOp::Mul, Value::Int(-2), Value::Uint(0), checked).unwrap(), Value::Uint(0));
+			assert_eq!(mixed(BinOp::Div, Value::Uint(7), Value::Int(2), checked).unwrap(), Value::Uint(3));
+			assert_eq!(mixed(BinOp::Add, Value::Uint(u64::MAX), Value::Int(-1), checked).unwrap(), Value::Uint(u64::MAX - 1));
+		}
+	}
+	
+	#[test]
+	fn mixed_arithmetic_out_of_range() {
+		// wraps around unless checked
+		assert_eq!(mixed(BinOp::Add, Value::Uint(0), Value::Int(-1), false).unwrap(), Value::Uint(u64::MAX));
+		assert!(matches!(mixed(BinOp::Add, Value::Uint(0), Value::Int(-1), true), Err(EvalError::Overflow)));
+		assert!(matches!(mixed(BinOp::Mul, Value::Uint(2), Value::Int(-1), true), Err(EvalError::Overflow)));
+		assert!(matches!(mixed(BinOp::Add, Value::Uint(u64::MAX), Value::Int(1), true), Err(EvalError::Overflow)));
+		assert!(matches!(mixed(BinOp::Exp, Value::Uint(2), Value::Int(64), true), Err(EvalError::Overflow)));
+		assert!(matches!(mixed(BinOp::Exp, Value::Uint(2), Value::Int(-1), false), Err(EvalError::NegativeExponent(-1))));
+	}
+	
+	#[test]
+	fn mixed_shift_keeps_left_type() {
+		assert_eq!(mixed(BinOp::Shl, Value::Int(-1), Value::Uint(1), true).unwrap(), Value::Int(-2));
+		assert_eq!(mixed(BinOp::Shr, Value::Uint(4), Value::Int(1), true).unwrap(), Value::Uint(2));
+		assert!(matches!(mixed(BinOp::Shl, Value::Uint(1), Value::Int(-1), false), Err(EvalError::InvalidShift(-1))));
+	}
+}
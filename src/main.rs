@@ -21,8 +21,11 @@ use logos::Logos;
 
 //use crate::ast::Parse;
 //use crate::interpret;
+use crate::diagnostic::{Diagnostic, Level};
 use crate::token::Token;
 
+mod check;
+mod diagnostic;
 mod span;
 mod token;
 mod ast;
@@ -32,11 +35,24 @@ mod interpret;
 mod repl;
 
 fn main() -> io::Result<()> {
-	let mut args = env::args().skip(1);
+	let mut options = interpret::Options::default();
+	let mut args = Vec::new();
+	for arg in env::args().skip(1) {
+		match arg.as_str() {
+			"--transactional" => options.transactional = true,
+			"--checked" => options.checked = true,
+			_ if arg.starts_with("--") => {
+				eprintln!("error: unknown option `{}`", arg);
+				std::process::exit(1);
+			}
+			_ => args.push(arg),
+		}
+	}
+	let mut args = args.into_iter();
 	
 	match args.next() {
 		// start REPL
-		None => repl::init()?,
+		None => repl::init(&options)?,
 		
 		// interpret stdin
 		/*
@@ -59,27 +75,94 @@ fn main() -> io::Result<()> {
 		}
 		*/
 		
+		// statically check file without running it
+		Some(arg) if arg == "check" => {
+			let file = match args.next() {
+				Some(file) => file,
+				None => {
+					eprintln!("Usage: rever check <file>");
+					return Ok(())
+				}
+			};
+			
+			let source = std::fs::read_to_string(&file)?;
+			if compile(&file, &source).is_none() {
+				std::process::exit(1);
+			}
+		}
+		
 		// interpret file
 		Some(file) => {
-			use std::fs::read_to_string as open;
+			let source = std::fs::read_to_string(&file)?;
 			
-			let source = open(file)?;
-			let tokens = Token::lexer(&source);
-			let mut parser = ast::Parser::new(tokens);
-			
-			let ast = match parser.parse_file_module() {
-				Ok(ast) => ast,
-				Err(e) => {
-					eprintln!("Error: expected {}.", e);
-					eprintln!("Remaining source:\n{}", parser.tokens.remainder());
-					return Ok(())
-				}
+			let ast = match compile(&file, &source) {
+				Some(ast) => ast,
+				None => std::process::exit(1),
 			};
 			
 //			println!("{:#?}", ast);
-			interpret::interpret_file(ast.into());
+			if let Err(e) = interpret::interpret_file(ast, &options) {
+				report_runtime_error(&e, &file, &source);
+				std::process::exit(1);
+			}
 		}
 	}
 	
 	Ok(())
 }
+
+/// Prints a runtime error, followed by the Rever procedures that were running
+/// when it occurred, innermost first.
+fn report_runtime_error(e: &interpret::EvalError, file: &str, source: &str) {
+	match e.span() {
+		Some(span) =>
+			eprintln!("{}", Diagnostic::error(e.to_string(), span).render(file, source)),
+		None =>
+			eprintln!("error: {}", e),
+	}
+	
+	let frames = e.backtrace().iter()
+		.filter_map(|frame| frame.proc.as_ref().map(|proc| (proc, frame.span)));
+	for ((name, dir), span) in frames {
+		match span {
+			Some(span) => {
+				let (line, col) = span.line_col(source);
+				eprintln!("  in {} {}, at {}:{}:{}", dir, name, file, line, col);
+			}
+			None => eprintln!("  in {} {}", dir, name),
+		}
+	}
+}
+
+/// Parses and statically checks a file, printing any problems found. Returns
+/// the file's items if there were no errors.
+fn compile(file: &str, source: &str) -> Option<Vec<ast::Item>> {
+	let tokens = Token::lexer(source);
+	let mut parser = ast::Parser::new(tokens);
+	
+	let ast = match parser.parse_file_module() {
+		Ok(ast) => ast,
+		Err(errors) => {
+			for e in &errors {
+				eprintln!("{}\n", Diagnostic::error(e.to_string(), e.span).render(file, source));
+			}
+			eprintln!("aborting due to {} syntax error(s)", errors.len());
+			return None
+		}
+	};
+	
+	let problems = check::check_items(&ast);
+	for problem in &problems {
+		eprintln!("{}\n", problem.render(file, source));
+	}
+	
+	let errors = problems.iter()
+		.filter(|problem| problem.level == Level::Error)
+		.count();
+	if errors > 0 {
+		eprintln!("aborting due to {} error(s)", errors);
+		return None
+	}
+	
+	Some(ast)
+}
@@ -2,16 +2,17 @@ use std::io::{self, prelude::*};
 use logos::Logos;
 
 use crate::token::Token;
-use crate::ast::{self, LValue, Expr, Item, Module, Stmt};
-use crate::interpret::{Eval, EvalResult, Stack, StackFrame, Value};
+use crate::ast::{self, LValue, Expr, Item, Journal, Module, Stmt};
+use crate::interpret::{Eval, EvalResult, Options, Stack, StackFrame, Value};
 
-pub fn init() -> io::Result<()> {
+pub fn init(options: &Options) -> io::Result<()> {
 	let stdin = io::stdin();
 	let mut input = String::new();
 	let mut stdout = io::stdout();
 	let mut continuing = false;
 	
 	let mut module = Module::new("repl".into(), Vec::new());
+	module.checked = options.checked;
 	let mut stack = Stack::new();
 	let root_frame = StackFrame::new(Vec::new());
 	stack.push(root_frame);
@@ -23,7 +24,11 @@ pub fn init() -> io::Result<()> {
 		let prompt = if continuing { "|" } else { "<" };
 		print!("{} ", prompt);
 		stdout.flush()?;
-		stdin.read_line(&mut input)?;
+		if stdin.read_line(&mut input)? == 0 {
+			// end of input
+			println!();
+			return Ok(());
+		}
 		
 		// read
 		let tokens = Token::lexer(&input);
@@ -31,7 +36,7 @@ pub fn init() -> io::Result<()> {
 		
 		let line = match parser.parse_repl_line() {
 			Ok(line) => line,
-			Err(ast::ParseError::Eof) => {
+			Err(e) if e.is_eof() => {
 				continuing = true;
 				continue;
 			}
@@ -44,12 +49,18 @@ pub fn init() -> io::Result<()> {
 		};
 		
 		// eval
-		if line.eval(stack.last_mut().unwrap(), &mut module).is_ok() {
-			input.clear();
-		} else {
-			return Ok(());
+		if let Some(frame) = stack.last_mut() {
+			if let Err(e) = line.eval(frame, &mut module, options) {
+				eprintln!("! Error: {}.", e);
+				for frame in e.backtrace() {
+					if let Some((name, dir)) = &frame.proc {
+						eprintln!("!   in {} {}", dir, name);
+					}
+				}
+			}
 		}
 		
+		input.clear();
 		continuing = false;
 	}
 }
@@ -68,16 +79,26 @@ pub enum ReplLine {
 
 impl ast::Parser<'_> {
 	pub fn parse_repl_line(&mut self) -> ast::ParseResult<ReplLine> {
+		let line = self.parse_repl_line_inner();
+		
+		// report the first error, even if the parser recovered from it
+		match self.errors.drain(..).next() {
+			Some(e) => Err(e),
+			None => line,
+		}
+	}
+	
+	fn parse_repl_line_inner(&mut self) -> ast::ParseResult<ReplLine> {
 		Ok(match self.peek() {
 			None => todo!(),
 			Some(Token::Var) => {
 				self.next();
 				
 				let name = self.expect_ident()
-					.ok_or("variable name after `var`")?;
+					.ok_or_else(|| self.error("variable name after `var`"))?;
 				
 				self.expect(Token::Assign)
-					.ok_or("`:=` after variable name")?;
+					.ok_or_else(|| self.error("`:=` after variable name"))?;
 				
 				let init = self.parse_expr()?;
 				
@@ -87,7 +108,7 @@ impl ast::Parser<'_> {
 				self.next();
 				
 				let name = self.expect_ident()
-					.ok_or("variable name after `drop`")?;
+					.ok_or_else(|| self.error("variable name after `drop`"))?;
 				
 				ReplLine::Drop(name)
 			}
@@ -96,14 +117,15 @@ impl ast::Parser<'_> {
 				self.next();
 				
 				let name = self.expect_ident()
-					.ok_or("variable name after `show`")?;
+					.ok_or_else(|| self.error("variable name after `show`"))?;
 				
 				ReplLine::Show(name)
 			}
 			*/
 			Some(Token::Fn)
 			| Some(Token::Proc)
-			| Some(Token::Mod) => {
+			| Some(Token::Mod)
+			| Some(Token::Struct) => {
 				self.parse_item()?.into()
 			}
 				
@@ -115,14 +137,14 @@ impl ast::Parser<'_> {
 }
 
 impl ReplLine {
-	fn eval(self, t: &mut StackFrame, m: &mut Module) -> EvalResult<Value> {
+	fn eval(self, t: &mut StackFrame, m: &mut Module, options: &Options) -> EvalResult<Value> {
 		match self {
 			ReplLine::Show(lval) => {
-				println!(": {}", t.get(&lval)?);
+				println!(": {}", t.get(&lval, m)?);
 			}
 			
 			ReplLine::Var(name, expr) => {
-				let val = expr.eval(t)?;
+				let val = expr.eval(t, m)?;
 				t.push(name, val);
 			}
 			
@@ -133,6 +155,16 @@ impl ReplLine {
 			ReplLine::Item(item) => {
 				m.insert(item);
 			}
+			ReplLine::Stmt(stmt) if options.transactional => {
+				// undo the parts of a compound statement that already ran
+				let mut journal = Journal::new();
+				if let Err(e) = stmt.eval_recorded(t, m, &mut journal) {
+					return match journal.rollback(t, m) {
+						Ok(()) => Err(e),
+						Err(cause) => Err(e.rollback_failed(cause)),
+					};
+				}
+			}
 			ReplLine::Stmt(stmt) => {
 				stmt.eval(t, m)?;
 			}
@@ -1,7 +1,7 @@
 //use std::fmt;
 use std::ops::Range;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 pub struct Span {
 	pub start: usize,
 	pub end: usize,
@@ -10,18 +10,27 @@ pub struct Span {
 impl Span {
 	pub fn new(start: usize, len: usize) -> Span {
 		Span {
-			start: start,
+			start,
 			end: start + len,
 		}
 	}
 	
 	pub fn merge(&self, span: &Span) -> Span {
-		debug_assert!(self.start < span.end);
+		debug_assert!(self.start <= span.end);
 		Span {
 			start: self.start,
 			end: span.end,
 		}
 	}
+	
+	/// Returns the 1-based line and column of the start of the span.
+	pub fn line_col(&self, source: &str) -> (usize, usize) {
+		let before = &source[..self.start.min(source.len())];
+		let line = before.matches('\n').count() + 1;
+		let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+		let col = before[line_start..].chars().count() + 1;
+		(line, col)
+	}
 }
 
 impl From<Range<usize>> for Span {
@@ -16,26 +16,29 @@ pub enum Token {
 	#[token("end")]    End,
 	#[token("fi")]     Fi,
 	#[token("fn")]     Fn,
+	#[token("for")]    For,
 	#[token("from")]   From,
 	#[token("if")]     If,
+	#[token("in")]     In,
 	#[token("let")]    Let,
 	#[token("loop")]   Loop,
+	#[token("match")]  Match,
+	#[token("mod")]    Modulo,
 	#[token("module")] Mod,
 	#[token("not")]    Not,
 	#[token("or")]     Or,
 	#[token("proc")]   Proc,
 	#[token("skip")]   Skip,
+	#[token("struct")] Struct,
 	#[token("then")]   Then,
 	#[token("undo")]   Undo,
 	#[token("until")]  Until,
 	#[token("var")]    Var,
+	#[token("when")]   When,
+	#[token("xor")]    Xor,
 	
 	// reserved keywords
 	#[token("alias")] Alias,
-	#[token("for")]   For,
-	#[token("match")] Match,
-	#[token("when")]  When,
-//	#[token("struct")] Struct,
 	#[token("tag")]   Tag,
 //	#[token("union")]  Union,
 	//Goto,
@@ -71,6 +74,7 @@ pub enum Token {
 	#[token(":")]  Colon,
 	#[token(",")]  Comma,
 	#[token(";")]  Semicolon,
+	#[token("..")] Range,
 	
 	#[token("+")]  Plus,
 	#[token("-")]  Minus,
@@ -81,9 +85,12 @@ pub enum Token {
 	#[token("!")]  Bang,
 	#[token("^")]  Caret,
 	#[token("#")]  Hash,
+	#[token("&")]  Amp,
+	#[token("|")]  Pipe,
+	#[token("<<")] Shl,
+	#[token(">>")] Shr,
 	
 	// unused
-	#[token("..")] Range,
 	#[token("::")] Scope,
 	#[token("->")] RightArrow,
 	#[token("?")]  QMark,
@@ -97,7 +104,7 @@ pub enum Token {
 	
 	// literals
 	//#[regex("[0-9][0-9']*")]
-	#[regex("[0-9]+")]
+	#[regex("[0-9]+|0x[0-9A-Fa-f]+")]
 	Number,
 	#[regex(r#""(\\[ntr0"\\]|[^"\\])*""#)]
 	String,
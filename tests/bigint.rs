@@ -0,0 +1,87 @@
+mod common;
+
+use common::{run, run_err};
+
+#[test]
+fn arithmetic_beyond_64_bits() {
+	run("\
+proc main
+	var n: bigint := 1
+	n += (2 as bigint) ^ 100
+	n -= 1
+	if n = 1267650600228229401496703205376
+		skip
+	fi n = (2 as bigint) ^ 100
+	drop n := 1267650600228229401496703205376
+end
+");
+}
+
+#[test]
+fn big_literals_and_xor() {
+	run("\
+proc main
+	var m := 340282366920938463463374607431768211456
+	var n := (2 as bigint) ^ 128 + 5
+	m := n
+	drop n := (2 as bigint) ^ 128 + 5
+	drop m := 5
+end
+");
+}
+
+#[test]
+fn updates_undone_by_uncall() {
+	run("\
+proc grow(var n: bigint)
+	n += 18446744073709551616
+	n -= 1
+end
+
+proc main
+	var n: bigint := 7
+	do grow: n
+	do grow: n
+	undo grow: n
+	undo grow: n
+	drop n := 7
+end
+");
+}
+
+#[test]
+fn mixed_with_int() {
+	run("\
+proc main
+	var n := (2 as bigint) ^ 64
+	n -= 9223372036854775807
+	n -= 9223372036854775807
+	drop n := 2
+end
+");
+}
+
+#[test]
+fn cast_that_does_not_fit() {
+	let errors = run_err("\
+proc main
+	var n := (2 as bigint) ^ 70
+	var x := n as int
+	drop x := 0
+	drop n := (2 as bigint) ^ 70
+end
+");
+	assert!(errors.contains("1180591620717411303424 doesn't fit in type Int"), "{}", errors);
+}
+
+#[test]
+fn no_overflow_under_checked() {
+	let output = common::rever(&["--checked"], "\
+proc main
+	var n: bigint := 9223372036854775807
+	n += 9223372036854775807
+	drop n := 18446744073709551614
+end
+");
+	assert!(output.success, "{}", output.stderr);
+}
@@ -0,0 +1,74 @@
+mod common;
+
+use common::{check, run};
+
+const ADD: &str = "\
+proc add(var a: int, b: int)
+	a += b
+end
+";
+
+#[test]
+fn multiline_call() {
+	run(&format!("{}
+proc main
+	var x := 1
+	do add(
+		x,
+		2,
+	)
+	undo add(
+		x,
+		1
+	)
+	drop x := 2
+end
+", ADD));
+}
+
+#[test]
+fn all_forms_agree() {
+	run(&format!("{}
+proc main
+	var x := 0
+	do add: x, 1
+	do add(x, 2)
+	do add(
+		x, 3
+	)
+	drop x := 6
+end
+", ADD));
+}
+
+#[test]
+fn multiline_call_without_args() {
+	assert_eq!(run("\
+proc nothing
+	skip
+end
+
+proc main
+	do nothing(
+	)
+	do show(
+		\"done\"
+	)
+end
+"), "done");
+}
+
+#[test]
+fn unclosed_multiline_call() {
+	let output = check(&format!("{}
+proc main
+	var x := 0
+	do add(
+		x,
+		1
+	drop x := 1
+end
+", ADD));
+	assert!(!output.success);
+	assert!(output.stderr.contains("expected"));
+}
@@ -0,0 +1,82 @@
+mod common;
+
+use common::{run, run_err};
+
+#[test]
+fn returned_closure() {
+	run("\
+fn adder(n: int): fn(int): int = fn(x): x + n
+
+proc main
+	var add5 := adder(5)
+	var x := add5(1)
+	drop x := 6
+	drop add5 := adder(5)
+end
+");
+}
+
+#[test]
+fn captures_by_value() {
+	run("\
+proc main
+	var n := 2
+	var double := fn(x): x * n
+	n += 1
+	var x := double(10)
+	drop x := 20
+	n -= 1
+	drop double := fn(x): x * n
+	drop n := 2
+end
+");
+}
+
+#[test]
+fn passed_as_argument() {
+	run("\
+fn apply(f: fn(int): int, x: int): int = f(x)
+
+proc bump(f: fn(int): int, n: int, var x: int)
+	x += f(n)
+end
+
+proc main
+	var x := 1
+	do bump: fn(y): y * 2, 1, x
+	do bump: fn(y): y * 2, 1, x
+	var y := apply(fn(a): a - 1, x)
+	drop y := 4
+	undo bump: fn(y): y * 2, 1, x
+	drop x := 3
+end
+");
+}
+
+#[test]
+fn wrong_number_of_arguments() {
+	let errors = run_err("\
+proc main
+	var f := fn(x): x
+	var y := f(1, 2)
+	drop y := 1
+	drop f := fn(x): x
+end
+");
+	assert!(errors.contains("takes 1 argument(s), but 2 were given"), "{}", errors);
+}
+
+#[test]
+fn drop_with_different_capture() {
+	let errors = run_err("\
+proc main
+	var n := 1
+	var f := fn(x): x + n
+	n += 1
+	drop f := fn(x): x + n
+	n -= 1
+	drop n := 1
+end
+");
+	assert!(errors.contains("`f` was dropped"), "{}", errors);
+}
@@ -0,0 +1,90 @@
+/*! Helpers for running Rever programs through the `rever` binary. */
+
+#![allow(dead_code)]
+
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// What a run of `rever` printed, and whether it succeeded.
+pub struct Output {
+	pub success: bool,
+	pub stdout: String,
+	pub stderr: String,
+}
+
+impl Output {
+	/// Number of diagnostics at the given level, e.g. `"error"`.
+	pub fn count(&self, level: &str) -> usize {
+		let prefix = format!("{}: ", level);
+		self.stderr.lines()
+			.filter(|line| line.starts_with(&prefix))
+			.count()
+	}
+}
+
+/// Runs `rever` with the given arguments, followed by the path of a file
+/// holding `source`.
+pub fn rever(args: &[&str], source: &str) -> Output {
+	static COUNT: AtomicUsize = AtomicUsize::new(0);
+	
+	let path: PathBuf = std::env::temp_dir().join(format!(
+		"rever-test-{}-{}.rvr",
+		std::process::id(),
+		COUNT.fetch_add(1, Ordering::SeqCst),
+	));
+	fs::write(&path, source).unwrap();
+	
+	let output = Command::new(env!("CARGO_BIN_EXE_rever"))
+		.args(args)
+		.arg(&path)
+		.output()
+		.unwrap();
+	fs::remove_file(&path).unwrap();
+	
+	Output {
+		success: output.status.success(),
+		stdout: String::from_utf8(output.stdout).unwrap(),
+		stderr: String::from_utf8(output.stderr).unwrap(),
+	}
+}
+
+/// Runs a program, panicking with its errors if it fails.
+pub fn run(source: &str) -> String {
+	let output = rever(&[], source);
+	assert!(output.success, "program failed:\n{}", output.stderr);
+	output.stdout
+}
+
+/// Runs a program that's expected to fail, returning its errors.
+pub fn run_err(source: &str) -> String {
+	let output = rever(&[], source);
+	assert!(!output.success, "program succeeded:\n{}", output.stdout);
+	output.stderr
+}
+
+/// Checks a program without running it.
+pub fn check(source: &str) -> Output {
+	rever(&["check"], source)
+}
+
+/// Runs the REPL with `args`, typing in `input`.
+pub fn repl(args: &[&str], input: &str) -> Output {
+	let mut child = Command::new(env!("CARGO_BIN_EXE_rever"))
+		.args(args)
+		.stdin(Stdio::piped())
+		.stdout(Stdio::piped())
+		.stderr(Stdio::piped())
+		.spawn()
+		.unwrap();
+	child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+	let output = child.wait_with_output().unwrap();
+	
+	Output {
+		success: output.status.success(),
+		stdout: String::from_utf8(output.stdout).unwrap(),
+		stderr: String::from_utf8(output.stderr).unwrap(),
+	}
+}
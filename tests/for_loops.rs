@@ -0,0 +1,89 @@
+mod common;
+
+use common::{run, run_err};
+
+#[test]
+fn range() {
+	run("\
+proc main
+	var total := 0
+	for i in 0..5
+		total += i
+	loop
+	drop total := 10
+end
+");
+}
+
+#[test]
+fn empty_range() {
+	run("\
+proc main
+	var total := 0
+	for i in 3..3
+		total += 1
+	loop
+	drop total := 0
+end
+");
+}
+
+#[test]
+fn array_elements() {
+	run("\
+proc main
+	var arr := [1, 2, 3]
+	var total := 0
+	for x in arr
+		total += x
+	loop
+	drop total := 6
+	drop arr := [1, 2, 3]
+end
+");
+}
+
+#[test]
+fn reverse_steps_backwards() {
+	run("\
+proc rotate(var a: _)
+	for i in 0..2
+		a.(i) <> a.(i + 1)
+	loop
+end
+
+proc main
+	var a := [1, 2, 3]
+	do rotate: a
+	undo rotate: a
+	undo rotate: a
+	drop a := [3, 1, 2]
+end
+");
+}
+
+#[test]
+fn loop_variable_is_immutable() {
+	let errors = run_err("\
+proc main
+	for i in 0..3
+		i += 1
+	loop
+end
+");
+	assert!(errors.contains("cannot modify `i`"), "{}", errors);
+}
+
+#[test]
+fn bounds_are_immutable() {
+	let errors = run_err("\
+proc main
+	var n := 3
+	for i in 0..n
+		n += 1
+	loop
+	drop n := 6
+end
+");
+	assert!(errors.contains("cannot modify `n`"), "{}", errors);
+}
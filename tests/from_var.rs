@@ -0,0 +1,80 @@
+mod common;
+
+use common::{run, run_err};
+
+#[test]
+fn counts_up() {
+	run("\
+proc main
+	var total := 0
+	from var i := 0
+		total += i
+		i += 1
+	until drop i := 5
+	loop
+	drop total := 10
+end
+");
+}
+
+#[test]
+fn runs_backwards() {
+	run("\
+proc fill(var a: _)
+	from var i := 0
+		a.(i) += i
+		i += 1
+	until drop i := 3
+	loop
+end
+
+proc main
+	var a := [0, 0, 0]
+	do fill: a
+	undo fill: a
+	drop a := [0, 0, 0]
+end
+");
+}
+
+#[test]
+fn typed_counter() {
+	run("\
+proc main
+	var total: u8 := 0
+	from var i: u8 := 250
+		total += i
+		i += 1
+	until drop i := 2
+	loop
+	drop total := 236
+end
+");
+}
+
+#[test]
+fn counter_of_wrong_type() {
+	let errors = run_err("\
+proc main
+	from var i: bool := 0
+		skip
+	until drop i := 1
+	loop
+end
+");
+	assert!(errors.contains("type mismatch"), "{}", errors);
+}
+
+#[test]
+fn counter_returns_to_initial_value() {
+	let errors = run_err("\
+proc main
+	from var i := 0
+		i += 1
+	until drop i := 5
+		i -= 1
+	loop
+end
+");
+	assert!(errors.contains("`from var` assertion"), "{}", errors);
+}
@@ -0,0 +1,92 @@
+mod common;
+
+use common::{run, run_err};
+
+const FNS: &str = "\
+fn square(x: int): int = x * x
+
+fn fact(n: int): int
+	if n = 0
+		1
+	else
+		n * fact(n - 1)
+	fi
+end
+
+fn hyp(a: int, b: int): int
+	let a2 = square(a)
+	let b2 = square(b)
+	a2 + b2
+end
+";
+
+#[test]
+fn call_in_expressions() {
+	run(&format!("{}
+proc main
+	var x := square(3) + fact(5)
+	var y := hyp(3, 4)
+	drop y := 25
+	drop x := 129
+end
+", FNS));
+}
+
+#[test]
+fn call_in_update_and_condition() {
+	run(&format!("{}
+proc main
+	var x := 0
+	x += fact(4)
+	if square(x) > 500
+		x -= 24
+	fi x = 0
+	drop x := 0
+end
+", FNS));
+}
+
+#[test]
+fn builtin_size() {
+	run("\
+proc main
+	var a := [1, 2, 3]
+	var n := size(a) + size(\"héllo\")
+	drop n := 8
+	drop a := [1, 2, 3]
+end
+");
+}
+
+#[test]
+fn wrong_number_of_arguments() {
+	let errors = run_err(&format!("{}
+proc main
+	var x := square(1, 2)
+	drop x := 1
+end
+", FNS));
+	assert!(errors.contains("`square` takes 1 argument(s), but 2 were given"), "{}", errors);
+}
+
+#[test]
+fn wrong_argument_type() {
+	let errors = run_err(&format!("{}
+proc main
+	var x := square(\"three\")
+	drop x := 9
+end
+", FNS));
+	assert!(errors.contains("type mismatch"), "{}", errors);
+}
+
+#[test]
+fn unknown_function() {
+	let errors = run_err("\
+proc main
+	var x := nope(1)
+	drop x := 1
+end
+");
+	assert!(errors.contains("no function named `nope`"), "{}", errors);
+}
@@ -0,0 +1,80 @@
+mod common;
+
+use common::{run, run_err};
+
+const SET: &str = "\
+proc set(var a: int)
+	a += 5
+end
+";
+
+#[test]
+fn var_then_drop() {
+	run(&format!("{}
+proc main
+	do set: var x
+	undo set: drop x
+end
+", SET));
+}
+
+#[test]
+fn var_is_live_after_call() {
+	run(&format!("{}
+proc main
+	do set: var x
+	x -= 5
+	x += 2
+	x -= 2
+	undo set: var y
+	do set: drop y
+	x += 5
+	undo set: drop x
+end
+", SET));
+}
+
+#[test]
+fn drop_of_nonzero_value() {
+	let errors = run_err(&format!("{}
+proc main
+	do set: var x
+	do set: drop x
+end
+", SET));
+	assert!(errors.contains("dropped"), "{}", errors);
+}
+
+#[test]
+fn var_of_existing_name() {
+	let errors = run_err(&format!("{}
+proc main
+	var x := 1
+	do set: var x
+	drop x := 1
+end
+", SET));
+	assert!(errors.contains("`x`"), "{}", errors);
+}
+
+#[test]
+fn var_of_record_type() {
+	run("\
+struct Point
+	x: int
+	y: int
+end
+
+proc move(var p: Point)
+	p.x += 3
+end
+
+proc main
+	do move: var p
+	var x := 3
+	x -= p.x
+	drop x := 0
+	undo move: drop p
+end
+");
+}
@@ -0,0 +1,86 @@
+mod common;
+
+use common::{run, run_err};
+
+#[test]
+fn update_string_char() {
+	assert_eq!(run("\
+proc main
+	var s := \"héllo\"
+	s.(1) += 1
+	do show: s
+	s.(0) -= 2
+	do show: s
+	s.(0) += 2
+	s.(1) -= 1
+	drop s := \"héllo\"
+end
+"), "hêllofêllo");
+}
+
+#[test]
+fn update_nested_array() {
+	run("\
+proc main
+	var grid := [[1, 2], [3, 4]]
+	var i := 1
+	grid.(i).(0) += 10
+	grid.(0).(i) := 6
+	drop i := 1
+	drop grid := [[1, 4], [13, 4]]
+end
+");
+}
+
+#[test]
+fn nested_update_undone_by_uncall() {
+	run("\
+proc bump(var grid: _)
+	grid.(0).(0) += 1
+	grid.(1).(1) -= 1
+end
+
+proc main
+	var grid := [[0, 0], [0, 0]]
+	do bump: grid
+	undo bump: grid
+	drop grid := [[0, 0], [0, 0]]
+end
+");
+}
+
+#[test]
+fn len_is_read_only() {
+	let errors = run_err("\
+proc main
+	var s := \"ab\"
+	s.len += 1
+	drop s := \"ab\"
+end
+");
+	assert!(errors.contains("field `len` can't be modified"), "{}", errors);
+}
+
+#[test]
+fn string_index_out_of_bounds() {
+	let errors = run_err("\
+proc main
+	var s := \"ab\"
+	s.(5) += 1
+	drop s := \"ab\"
+end
+");
+	assert!(errors.contains("index 5 is out of bounds for length 2"), "{}", errors);
+}
+
+#[test]
+fn index_into_int() {
+	let errors = run_err("\
+proc main
+	var n := 3
+	n.(0) += 1
+	drop n := 3
+end
+");
+	assert!(errors.contains("type mismatch"), "{}", errors);
+}
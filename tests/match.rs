@@ -0,0 +1,115 @@
+mod common;
+
+use common::{run, run_err};
+
+#[test]
+fn picks_arm_by_guard() {
+	run("\
+proc main
+	var x := 2
+	var y := 0
+	match
+	when x = 1
+		y += 10
+	when x = 2
+		y += 20
+	else
+		y += 30
+	end
+	drop y := 20
+	drop x := 2
+end
+");
+}
+
+#[test]
+fn compares_with_subject() {
+	run("\
+proc main
+	var c := '-'
+	var n := 5
+	match c
+	when '+'
+		n += 1
+	when '-'
+		n -= 1
+	end
+	drop n := 4
+	drop c := '-'
+end
+");
+}
+
+#[test]
+fn else_arm() {
+	run("\
+proc main
+	var c := 'x'
+	var n := 5
+	match c
+	when '+'
+		n += 1
+	else
+		skip
+	end
+	drop n := 5
+	drop c := 'x'
+end
+");
+}
+
+#[test]
+fn exit_assertion_picks_arm_in_reverse() {
+	run("\
+proc step(var x: int)
+	match x
+	when 0 then 1
+		x += 1
+	when 1 then 2
+		x += 1
+	end
+end
+
+proc main
+	var x := 0
+	do step: x
+	do step: x
+	undo step: x
+	drop x := 1
+end
+");
+}
+
+#[test]
+fn no_guard_holds() {
+	let errors = run_err("\
+proc main
+	var x := 3
+	match x
+	when 1
+		skip
+	when 2
+		skip
+	end
+	drop x := 3
+end
+");
+	assert!(errors.contains("0 `when` guards held"), "{}", errors);
+}
+
+#[test]
+fn two_exit_assertions_hold() {
+	let errors = run_err("\
+proc main
+	var x := 0
+	match
+	when x = 0 then x = 1
+		x += 1
+	when x = 5 then x > 0
+		skip
+	end
+	drop x := 1
+end
+");
+	assert!(errors.contains("2 `match` exit assertions held"), "{}", errors);
+}
@@ -0,0 +1,83 @@
+mod common;
+
+use common::{run, run_err};
+
+#[test]
+fn pass_proc_to_param() {
+	run("\
+proc inc(var x: int)
+	x += 1
+end
+
+proc twice(p: proc(var int), var x: int)
+	do p: x
+	do p: x
+end
+
+proc main
+	var x := 0
+	do twice: inc, x
+	undo twice: inc, x
+	undo twice: inc, x
+	drop x := -2
+end
+");
+}
+
+#[test]
+fn undo_proc_in_variable() {
+	run("\
+proc inc(var x: int)
+	x += 1
+end
+
+proc main
+	var p := inc
+	var x := 5
+	undo p: x
+	drop x := 4
+	drop p := inc
+end
+");
+}
+
+#[test]
+fn signature_mismatch() {
+	let errors = run_err("\
+proc add(var x: int, n: int)
+	x += n
+end
+
+proc twice(p: proc(var int), var x: int)
+	do p: x
+	do p: x
+end
+
+proc main
+	var x := 0
+	do twice: add, x
+	drop x := 0
+end
+");
+	assert!(errors.contains("type mismatch"), "{}", errors);
+}
+
+#[test]
+fn aliased_arguments_to_param() {
+	let errors = run_err("\
+proc add(var x: int, y: int)
+	x += y
+end
+
+proc apply(p: proc(var int, int), var a: int)
+	do p: a, a
+end
+
+proc main
+	var x := 5
+	do apply: add, x
+	drop x := 5
+end
+");
+	assert!(errors.contains("`a` is passed more than once"), "{}", errors);
+}
@@ -0,0 +1,133 @@
+mod common;
+
+use common::{check, run, run_err};
+
+#[test]
+fn update_and_swap_fields() {
+	run("\
+struct Point
+	x: int
+	y: int
+end
+
+proc main
+	var p := Point { x: 1, y: 2 }
+	p.x += 10
+	p.x <> p.y
+	drop p := Point { x: 2, y: 11 }
+end
+");
+}
+
+#[test]
+fn fields_in_any_order() {
+	run("\
+struct Point
+	x: int
+	y: int
+end
+
+proc main
+	var p := Point { y: 2, x: 1 }
+	drop p := Point { x: 1, y: 2 }
+end
+");
+}
+
+#[test]
+fn structural_equality() {
+	run("\
+struct Point
+	x: int
+	y: int
+end
+
+proc main
+	var p := Point { x: 1, y: 2 }
+	var q := Point { x: 1, y: 2 }
+	var r := Point { x: 2, y: 1 }
+	var n := 0
+	if p = q
+		n += 1
+	fi p = q
+	if p = r
+		n += 10
+	fi p = r
+	drop n := 1
+	drop r := Point { x: 2, y: 1 }
+	drop q := Point { x: 1, y: 2 }
+	drop p := Point { x: 1, y: 2 }
+end
+");
+}
+
+#[test]
+fn field_as_var_argument() {
+	run("\
+struct Point
+	x: int
+	y: int
+end
+
+proc bump(var n: int)
+	n += 1
+end
+
+proc main
+	var p := Point { x: 1, y: 2 }
+	do bump: p.y
+	drop p := Point { x: 1, y: 3 }
+end
+");
+}
+
+#[test]
+fn missing_field() {
+	let errors = run_err("\
+struct Point
+	x: int
+	y: int
+end
+
+proc main
+	var p := Point { x: 1 }
+	drop p := Point { x: 1, y: 0 }
+end
+");
+	assert!(errors.contains("missing field `y`"), "{}", errors);
+}
+
+#[test]
+fn unknown_field() {
+	let errors = run_err("\
+struct Point
+	x: int
+	y: int
+end
+
+proc main
+	var p := Point { x: 1, y: 0 }
+	p.z += 1
+	drop p := Point { x: 1, y: 0 }
+end
+");
+	assert!(errors.contains("has no field `z`"), "{}", errors);
+}
+
+#[test]
+fn unknown_types() {
+	let output = check("\
+struct Point
+	x: int
+	y: Pair
+end
+
+proc main(p: Size)
+	skip
+end
+");
+	assert!(!output.success);
+	assert_eq!(output.count("error"), 2);
+	assert!(output.stderr.contains("unknown type `Pair`"), "{}", output.stderr);
+	assert!(output.stderr.contains("unknown type `Size`"), "{}", output.stderr);
+}
@@ -0,0 +1,61 @@
+mod common;
+
+use common::check;
+
+#[test]
+fn reports_errors_in_separate_statements() {
+	let output = check("\
+proc main
+	x += )
+	skip
+	y := := 1
+end
+");
+	assert!(!output.success);
+	assert_eq!(output.count("error"), 2);
+	assert!(output.stderr.contains("aborting due to 2 syntax error(s)"));
+}
+
+#[test]
+fn reports_errors_in_separate_items() {
+	let output = check("\
+proc f
+	x += )
+end
+
+proc
+	skip
+end
+
+proc g
+	y += )
+end
+");
+	assert_eq!(output.count("error"), 3);
+}
+
+#[test]
+fn keeps_parsing_after_error_in_nested_block() {
+	let output = check("\
+proc main
+	if x = 0
+		y += )
+	fi x = 0
+	z += )
+end
+");
+	assert_eq!(output.count("error"), 2);
+	assert!(output.stderr.contains(":3:8"));
+	assert!(output.stderr.contains(":5:7"));
+}
+
+#[test]
+fn valid_program_has_no_errors() {
+	let output = check("\
+proc main
+	skip
+end
+");
+	assert!(output.success, "{}", output.stderr);
+	assert_eq!(output.count("error"), 0);
+}
@@ -0,0 +1,91 @@
+mod common;
+
+use common::{run, run_err};
+
+#[test]
+fn modify_cell_through_parameter() {
+	run("\
+proc incr(r: ref int)
+	r! += 1
+end
+
+proc main
+	var r := nil as ref int
+	do alloc: r
+	do incr: r
+	do incr: r
+	undo incr: r
+	r! -= 1
+	do free: r
+	drop r := nil as ref int
+end
+");
+}
+
+#[test]
+fn undo_alloc_frees() {
+	run("\
+proc main
+	var r := nil as ref int
+	do alloc: r
+	undo alloc: r
+	drop r := nil as ref int
+end
+");
+}
+
+#[test]
+fn alloc_free_loop() {
+	run("\
+proc main
+	var r := nil as ref int
+	from var i := 0
+		do alloc: r
+		r! += i
+		r! -= i
+		do free: r
+		i += 1
+	until drop i := 1000
+	loop
+	drop r := nil as ref int
+end
+");
+}
+
+#[test]
+fn free_of_nonzero_cell() {
+	let errors = run_err("\
+proc main
+	var r := nil as ref int
+	do alloc: r
+	r! += 1
+	do free: r
+	drop r := nil as ref int
+end
+");
+	assert!(errors.contains("can't free a cell holding 1"), "{}", errors);
+}
+
+#[test]
+fn deref_of_null() {
+	let errors = run_err("\
+proc main
+	var r := nil as ref int
+	r! += 1
+	drop r := nil as ref int
+end
+");
+	assert!(errors.contains("dereferenced a null reference"), "{}", errors);
+}
+
+#[test]
+fn rotate_by_fixed_width_amount() {
+	run("\
+proc main
+	var x: u8 := 0x81
+	x :< 1 as u8
+	x :> 2 as uint
+	drop x := 0xc0
+end
+");
+}
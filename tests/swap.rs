@@ -0,0 +1,104 @@
+mod common;
+
+use common::{run, run_err};
+
+#[test]
+fn swap_variables() {
+	run("\
+proc main
+	var a := 1
+	var b := 2
+	a <> b
+	drop b := 1
+	drop a := 2
+end
+");
+}
+
+#[test]
+fn swap_array_elements() {
+	run("\
+proc main
+	var a := [1, 2, 3]
+	var i := 0
+	a.(i) <> a.(2)
+	drop i := 0
+	drop a := [3, 2, 1]
+end
+");
+}
+
+#[test]
+fn swap_nested_and_between_arrays() {
+	run("\
+proc main
+	var a := [[1, 2], [3, 4]]
+	var b := [5, 6]
+	a.(0).(1) <> b.(0)
+	a.(1) <> b
+	drop b := [3, 4]
+	drop a := [[1, 5], [2, 6]]
+end
+");
+}
+
+#[test]
+fn swap_element_with_its_own_index() {
+	// the index is found before either side changes
+	run("\
+proc main
+	var a := [1, 0]
+	var i := 0
+	i <> a.(i)
+	drop i := 1
+	drop a := [0, 0]
+end
+");
+}
+
+#[test]
+fn swap_is_its_own_inverse() {
+	run("\
+proc rotate(var a: int, var b: int, var c: int)
+	a <> b
+	b <> c
+end
+
+proc main
+	var a := 1
+	var b := 2
+	var c := 3
+	do rotate: a, b, c
+	undo rotate: a, b, c
+	drop c := 3
+	drop b := 2
+	drop a := 1
+end
+");
+}
+
+#[test]
+fn swap_of_different_types() {
+	let errors = run_err("\
+proc main
+	var a := 1
+	var b := \"one\"
+	a <> b
+	drop b := \"one\"
+	drop a := 1
+end
+");
+	assert!(errors.contains("type mismatch"), "{}", errors);
+}
+
+#[test]
+fn swap_out_of_bounds() {
+	let errors = run_err("\
+proc main
+	var a := [1, 2]
+	a.(0) <> a.(2)
+	drop a := [1, 2]
+end
+");
+	assert!(errors.contains("index 2 is out of bounds"), "{}", errors);
+}
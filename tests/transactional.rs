@@ -0,0 +1,50 @@
+mod common;
+
+use common::{repl, rever};
+
+/// Fails part-way through an `if`, then checks whether `x` is back at zero
+/// by handing it to a `drop` argument.
+const PARTIAL_IF: &str = "\
+proc keep(var a: int)
+	skip
+end
+var x := 0
+if x = 0
+	x += 1
+	y += 1
+fi x = 1
+do keep: drop x
+";
+
+#[test]
+fn repl_rolls_back_failed_statement() {
+	let output = repl(&["--transactional"], PARTIAL_IF);
+	assert!(output.stderr.contains("unknown identifier `y`"), "{}", output.stderr);
+	assert!(!output.stderr.contains("dropped"), "{}", output.stderr);
+}
+
+#[test]
+fn repl_keeps_partial_effects_by_default() {
+	let output = repl(&[], PARTIAL_IF);
+	assert!(output.stderr.contains("unknown identifier `y`"), "{}", output.stderr);
+	assert!(output.stderr.contains("`x` was dropped with value 1"), "{}", output.stderr);
+}
+
+#[test]
+fn original_error_is_reported() {
+	let output = rever(&["--transactional"], "\
+proc fail(var a: int)
+	a += 1
+	do missing
+end
+
+proc main
+	var x := 0
+	do fail: x
+	drop x := 0
+end
+");
+	assert!(!output.success);
+	assert!(output.stderr.contains("no procedure named `missing`"), "{}", output.stderr);
+	assert!(output.stderr.contains("in do fail"), "{}", output.stderr);
+}